@@ -0,0 +1,142 @@
+use std::io::Cursor;
+use std::path::PathBuf;
+use colored::Colorize;
+use dialoguer::Select;
+use dialoguer::theme::ColorfulTheme;
+use crate::error::GarError;
+use crate::github::{Artifact, GitHub, RunFilter, WorkflowRun};
+use crate::helpers::{did_you_mean, extract_archive, format_size};
+use crate::output::print_records;
+use super::command::Command;
+
+pub(crate) struct ArtifactsOptions {
+    pub(crate) run_id: Option<u64>,
+    pub(crate) latest: bool,
+    pub(crate) name: Option<String>,
+    pub(crate) all: bool,
+    pub(crate) list: bool,
+    pub(crate) out: PathBuf,
+}
+
+pub struct ArtifactsCommand {
+    command: Command,
+    options: ArtifactsOptions,
+}
+
+impl ArtifactsCommand {
+    pub fn new(command: Command, options: ArtifactsOptions) -> Self {
+        ArtifactsCommand { command, options }
+    }
+
+    pub async fn run(&self) -> Result<(), GarError> {
+        let github = self.command.github();
+        let run = self.select_run(&github).await?;
+        self.status(format!("Run: {} ({}) {}", run.id, run.head_branch, run.html_url));
+
+        let artifacts = github.get_run_artifacts(run.id).await?;
+        if artifacts.is_empty() && self.command.output.is_table() {
+            println!("{}", "The run has no artifacts".yellow());
+            return Ok(());
+        }
+
+        let rows: Vec<Vec<String>> = artifacts.iter().map(|artifact| {
+            let expires_at = match (&artifact.expires_at, artifact.expired) {
+                (_, true) => "expired".to_string(),
+                (Some(expires_at), false) => expires_at.clone(),
+                (None, false) => "N/A".to_string(),
+            };
+            vec![
+                artifact.id.to_string(),
+                artifact.name.clone(),
+                format_size(artifact.size_in_bytes),
+                artifact.created_at.clone().unwrap_or_else(|| "N/A".to_string()),
+                expires_at,
+            ]
+        }).collect();
+        print_records(self.command.output, &artifacts, &["ID", "Name", "Size", "Created At", "Expires At"], &rows)?;
+
+        if self.options.list {
+            return Ok(());
+        }
+        for artifact in self.select_artifacts(&artifacts)? {
+            self.download(&github, artifact).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn select_run(&self, github: &GitHub) -> Result<WorkflowRun, GarError> {
+        if self.options.run_id.is_some() || !self.options.latest {
+            return github.resolve_run(self.options.run_id, self.command.workflow.as_deref(), &["completed"]).await;
+        }
+
+        let workflow = github.select_workflow(self.command.workflow.as_deref()).await?;
+        let branch = self.command.ref_name.trim_start_matches("refs/heads/").to_string();
+        let filter = RunFilter {
+            branch: Some(branch.clone()),
+            status: Some("success".to_string()),
+            ..RunFilter::default()
+        };
+        github.get_workflow_runs(workflow.id, &filter, Some(1)).await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| GarError::Selection(format!("No successful run of \"{}\" on \"{}\"", workflow.name, branch)))
+    }
+
+    // An empty selection only lists the artifacts
+    fn select_artifacts<'a>(&self, artifacts: &'a [Artifact]) -> Result<Vec<&'a Artifact>, GarError> {
+        if self.options.all {
+            return Ok(artifacts.iter().collect());
+        }
+        if let Some(name) = &self.options.name {
+            return match artifacts.iter().find(|artifact| &artifact.name == name) {
+                Some(artifact) => Ok(vec![artifact]),
+                None => {
+                    let message = did_you_mean(format!("The run has no artifact named \"{}\"", name), name, artifacts.iter().map(|artifact| artifact.name.as_str()));
+                    Err(GarError::Selection(message))
+                }
+            };
+        }
+        if !self.command.interactive || !self.command.output.is_table() {
+            return Ok(Vec::new());
+        }
+
+        let mut items: Vec<String> = artifacts.iter().map(|artifact| format!("{} ({})", artifact.name, format_size(artifact.size_in_bytes))).collect();
+        items.push("All artifacts".to_string());
+        items.push("None".to_string());
+        let selected = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt("Download:")
+            .items(&items)
+            .default(0)
+            .interact()?;
+
+        Ok(match selected {
+            i if i < artifacts.len() => vec![&artifacts[i]],
+            i if i == artifacts.len() => artifacts.iter().collect(),
+            _ => Vec::new(),
+        })
+    }
+
+    async fn download(&self, github: &GitHub, artifact: &Artifact) -> Result<(), GarError> {
+        if artifact.expired {
+            self.status(format!("Skipping \"{}\": the artifact has expired", artifact.name).yellow().to_string());
+            return Ok(());
+        }
+
+        self.status(format!("Downloading \"{}\" ({})...", artifact.name, format_size(artifact.size_in_bytes)));
+        let data = github.github_request_bytes(&artifact.archive_download_url, "GET", None, None).await?;
+        let dir = self.options.out.join(&artifact.name);
+        let files = extract_archive(Cursor::new(data), &dir)?;
+        self.status(format!("Extracted {} files to {}", files.len(), dir.display()).green().to_string());
+        Ok(())
+    }
+
+    // Progress goes to stderr when stdout carries machine-readable output
+    fn status(&self, message: String) {
+        if self.command.output.is_table() {
+            println!("{}", message);
+        } else {
+            eprintln!("{}", message);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use std::{env, fs};
+use crate::error::GarError;
 use super::command::Command;
 
 pub struct AutocompleteCommand {
@@ -10,7 +11,7 @@ impl AutocompleteCommand {
         AutocompleteCommand { _command }
     }
 
-    pub fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn run(&self) -> Result<(), GarError> {
 
 
         let current_dir = env::current_dir()?;
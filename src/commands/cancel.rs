@@ -0,0 +1,78 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use tokio::sync::Mutex;
+use crate::error::GarError;
+use crate::helpers::update_progress_bar;
+use super::command::Command;
+
+// Statuses of runs that can still be cancelled
+const ACTIVE_STATUSES: [&str; 5] = ["queued", "in_progress", "waiting", "requested", "pending"];
+const DEFAULT_CANCEL_TIMEOUT: Duration = Duration::from_secs(300);
+
+pub struct CancelCommand {
+    command: Command,
+    run_id: Option<u64>,
+    force: bool,
+}
+
+impl CancelCommand {
+    pub fn new(command: Command, run_id: Option<u64>, force: bool) -> Self {
+        CancelCommand { command, run_id, force }
+    }
+
+    pub async fn run(&self) -> Result<(), GarError> {
+        let github = self.command.github();
+
+        let run = github.resolve_run(self.run_id, self.command.workflow.as_deref(), &ACTIVE_STATUSES).await?;
+
+        if run.status == "completed" {
+            println!("Run {} already completed with conclusion: {}", run.id, run.conclusion.unwrap_or_else(|| "unknown".to_string()));
+            return Ok(());
+        }
+
+        let action = if self.force { "Force-cancel" } else { "Cancel" };
+        let prompt = format!("{} \"{}\" run {} ({})?", action, run.name, run.id, run.html_url);
+        if !self.command.confirm(&prompt, &format!("cancel run {}", run.id))? {
+            return Ok(());
+        }
+
+        github.cancel_run(run.id, self.force).await?;
+        println!("{} requested for {}", action, run.html_url);
+
+        let pb = Arc::new(Mutex::new(ProgressBar::new_spinner()));
+        pb.lock().await.set_style(ProgressStyle::default_spinner()
+            .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏")
+            .template("{spinner} Waiting for the run to be cancelled...").unwrap());
+        let pb_clone = Arc::clone(&pb);
+        let ticker = tokio::spawn(async move {
+            update_progress_bar(pb_clone).await;
+        });
+
+        let timeout = self.command.timeout.unwrap_or(DEFAULT_CANCEL_TIMEOUT);
+        let started = Instant::now();
+        let result = loop {
+            match github.get_workflow_run(run.id).await {
+                Ok(current) if current.status == "completed" => break Ok(current),
+                Ok(_) => {}
+                Err(e) => break Err(e),
+            }
+            if started.elapsed() >= timeout {
+                break Err(GarError::Timeout(format!("Run {} was not cancelled within {}s; try again with --force", run.id, timeout.as_secs())));
+            }
+            tokio::time::sleep(github.poll_interval(Duration::from_secs(3))).await;
+        };
+
+        ticker.abort();
+        pb.lock().await.finish_and_clear();
+
+        let current = result?;
+        match current.conclusion.as_deref() {
+            Some("cancelled") => println!("{}", format!("Run {} cancelled", current.id).green()),
+            conclusion => println!("{}", format!("Run {} completed before it could be cancelled, conclusion: {}", current.id, conclusion.unwrap_or("unknown")).yellow()),
+        }
+
+        Ok(())
+    }
+}
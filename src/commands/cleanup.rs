@@ -0,0 +1,180 @@
+use std::time::Duration;
+use colored::Colorize;
+use regex::Regex;
+use crate::error::GarError;
+use crate::github::{Artifact, Cache};
+use crate::helpers::{age_seconds, format_size};
+use crate::output::print_records;
+use super::command::Command;
+
+pub(crate) struct CleanupOptions {
+    pub(crate) name: Option<String>,
+    pub(crate) key: Option<String>,
+    pub(crate) ref_name: Option<String>,
+    pub(crate) older_than: Option<Duration>,
+    pub(crate) larger_than: Option<u64>,
+    pub(crate) dry_run: bool,
+}
+
+impl CleanupOptions {
+    fn matches_age(&self, timestamp: &Option<String>) -> bool {
+        match self.older_than {
+            Some(older_than) => age_seconds(timestamp).is_some_and(|age| age >= older_than.as_secs() as i64),
+            None => true,
+        }
+    }
+
+    fn matches_size(&self, size: u64) -> bool {
+        self.larger_than.is_none_or(|larger_than| size >= larger_than)
+    }
+}
+
+pub struct CleanupArtifactsCommand {
+    command: Command,
+    options: CleanupOptions,
+}
+
+impl CleanupArtifactsCommand {
+    pub fn new(command: Command, options: CleanupOptions) -> Self {
+        CleanupArtifactsCommand { command, options }
+    }
+
+    pub async fn run(&self) -> Result<(), GarError> {
+        let name = self.options.name.as_deref()
+            .map(Regex::new)
+            .transpose()
+            .map_err(|e| GarError::Selection(format!("Invalid name pattern: {}", e)))?;
+        let branch = self.options.ref_name.as_deref().map(|r| r.trim_start_matches("refs/heads/"));
+
+        let github = self.command.github();
+        let mut artifacts: Vec<Artifact> = github.get_artifacts().await?
+            .into_iter()
+            // Expired artifacts no longer count against the storage quota
+            .filter(|artifact| !artifact.expired)
+            .filter(|artifact| name.as_ref().is_none_or(|name| name.is_match(&artifact.name)))
+            .filter(|artifact| branch.is_none_or(|branch| {
+                artifact.workflow_run.as_ref().and_then(|run| run.head_branch.as_deref()) == Some(branch)
+            }))
+            .filter(|artifact| self.options.matches_age(&artifact.created_at))
+            .filter(|artifact| self.options.matches_size(artifact.size_in_bytes))
+            .collect();
+        if artifacts.is_empty() {
+            println!("{}", "No artifacts match the filters".green());
+            return Ok(());
+        }
+        artifacts.sort_by_key(|artifact| std::cmp::Reverse(artifact.size_in_bytes));
+
+        let rows: Vec<Vec<String>> = artifacts.iter().map(|artifact| {
+            let run = artifact.workflow_run.as_ref();
+            vec![
+                artifact.id.to_string(),
+                artifact.name.clone(),
+                format_size(artifact.size_in_bytes),
+                artifact.created_at.clone().unwrap_or_else(|| "N/A".to_string()),
+                run.map(|run| run.id.to_string()).unwrap_or_default(),
+                run.and_then(|run| run.head_branch.clone()).unwrap_or_default(),
+            ]
+        }).collect();
+        print_records(self.command.output, &artifacts, &["ID", "Name", "Size", "Created At", "Run", "Branch"], &rows)?;
+
+        let total: u64 = artifacts.iter().map(|artifact| artifact.size_in_bytes).sum();
+        if !confirm_cleanup(&self.command, &self.options, "artifacts", artifacts.len(), total)? {
+            return Ok(());
+        }
+
+        let mut freed = 0;
+        let mut deleted = 0;
+        for artifact in &artifacts {
+            match github.delete_artifact(artifact.id).await {
+                Ok(()) => {
+                    deleted += 1;
+                    freed += artifact.size_in_bytes;
+                }
+                Err(e) => eprintln!("{}", format!("Could not delete artifact \"{}\" ({}): {}", artifact.name, artifact.id, e).yellow()),
+            }
+        }
+        report(&self.command, "artifacts", deleted, artifacts.len(), freed)
+    }
+}
+
+pub struct CleanupCachesCommand {
+    command: Command,
+    options: CleanupOptions,
+}
+
+impl CleanupCachesCommand {
+    pub fn new(command: Command, options: CleanupOptions) -> Self {
+        CleanupCachesCommand { command, options }
+    }
+
+    pub async fn run(&self) -> Result<(), GarError> {
+        // Caches are scoped to full refs, a bare branch name is taken as refs/heads/<branch>
+        let ref_name = self.options.ref_name.as_ref().map(|r| {
+            if r.starts_with("refs/") { r.clone() } else { format!("refs/heads/{}", r) }
+        });
+
+        let github = self.command.github();
+        let mut caches: Vec<Cache> = github.get_caches(self.options.key.as_deref(), ref_name.as_deref()).await?
+            .into_iter()
+            // An unused cache is what is worth removing, so age counts from the last access
+            .filter(|cache| self.options.matches_age(&cache.last_accessed_at))
+            .filter(|cache| self.options.matches_size(cache.size_in_bytes))
+            .collect();
+        if caches.is_empty() {
+            println!("{}", "No caches match the filters".green());
+            return Ok(());
+        }
+        caches.sort_by_key(|cache| std::cmp::Reverse(cache.size_in_bytes));
+
+        let rows: Vec<Vec<String>> = caches.iter().map(|cache| vec![
+            cache.id.to_string(),
+            cache.key.clone(),
+            cache.ref_name.clone(),
+            format_size(cache.size_in_bytes),
+            cache.created_at.clone().unwrap_or_else(|| "N/A".to_string()),
+            cache.last_accessed_at.clone().unwrap_or_else(|| "N/A".to_string()),
+        ]).collect();
+        print_records(self.command.output, &caches, &["ID", "Key", "Ref", "Size", "Created At", "Last Accessed At"], &rows)?;
+
+        let total: u64 = caches.iter().map(|cache| cache.size_in_bytes).sum();
+        if !confirm_cleanup(&self.command, &self.options, "caches", caches.len(), total)? {
+            return Ok(());
+        }
+
+        let mut freed = 0;
+        let mut deleted = 0;
+        for cache in &caches {
+            match github.delete_cache(cache.id).await {
+                Ok(()) => {
+                    deleted += 1;
+                    freed += cache.size_in_bytes;
+                }
+                Err(e) => eprintln!("{}", format!("Could not delete cache \"{}\" ({}): {}", cache.key, cache.id, e).yellow()),
+            }
+        }
+        report(&self.command, "caches", deleted, caches.len(), freed)
+    }
+}
+
+// Prints what would be freed and asks before deleting; a dry run stops here
+fn confirm_cleanup(command: &Command, options: &CleanupOptions, kind: &str, count: usize, total: u64) -> Result<bool, GarError> {
+    // Keeps stdout to the listing when it is machine-readable
+    let summary = format!("{} {} matched, {} would be freed", count, kind, format_size(total)).bold();
+    if command.output.is_table() { println!("{}", summary) } else { eprintln!("{}", summary) }
+    if options.dry_run {
+        if command.output.is_table() {
+            println!("{}", "Dry run, nothing was deleted".dimmed());
+        }
+        return Ok(false);
+    }
+    command.confirm_or(&format!("Delete {} {} ({})?", count, kind, format_size(total)), &format!("delete {}", kind), "--yes or --dry-run")
+}
+
+fn report(command: &Command, kind: &str, deleted: usize, matched: usize, freed: u64) -> Result<(), GarError> {
+    let summary = format!("Deleted {} of {} {}, freed {}", deleted, matched, kind, format_size(freed)).green();
+    if command.output.is_table() { println!("{}", summary) } else { eprintln!("{}", summary) }
+    if deleted < matched {
+        return Err(GarError::Other(format!("{} {} could not be deleted", matched - deleted, kind)));
+    }
+    Ok(())
+}
@@ -1,5 +1,17 @@
-use clap::{Arg, Command as CommandClap, value_parser};
+use std::env;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::time::Duration;
+use clap::{Arg, ArgAction, Command as CommandClap, value_parser};
+use colored::Colorize;
+use dialoguer::Confirm;
+use dialoguer::theme::ColorfulTheme;
+use crate::error::GarError;
 use crate::git::Git;
+use crate::github::{GitHub, DEFAULT_API_URL, DEFAULT_PER_PAGE};
+use crate::helpers::{parse_duration, parse_size};
+use crate::log_parser::TimestampMode;
+use crate::output::OutputFormat;
 
 
 pub struct Command {
@@ -7,21 +19,93 @@ pub struct Command {
     pub owner: String,
     pub repo: String,
     pub ref_name: String,
+    pub api_url: String,
+    pub per_page: u32,
+    pub max_items: Option<usize>,
+    pub workflow: Option<String>,
+    pub assume_yes: bool,
+    pub interactive: bool,
+    pub timeout: Option<Duration>,
+    pub output: OutputFormat,
 }
 
 impl Command {
-    pub fn new(matches: &clap::ArgMatches) -> Self {
-        let token = matches.get_one::<String>("token").map(|s| s.to_owned()).unwrap_or_else(|| Git::get_token().unwrap());
-        let owner = matches.get_one::<String>("owner").map(|s| s.to_owned()).unwrap_or_else(|| Git::get_git_owner().unwrap());
-        let repo = matches.get_one::<String>("repo").map(|s| s.to_owned()).unwrap_or_else(|| Git::get_git_repo().unwrap());
-        let ref_name = matches.get_one::<String>("ref").map(|s| s.to_owned()).unwrap_or_else(|| Git::get_git_tree_name().unwrap_or("main".parse().unwrap()));
+    pub fn new(matches: &clap::ArgMatches) -> Result<Self, GarError> {
+        // common args are declared on every leaf subcommand, so read them from the one that was invoked
+        let mut matches = matches;
+        while let Some((_, sub_matches)) = matches.subcommand() {
+            matches = sub_matches;
+        }
+        let get = |name: &str| matches.try_get_one::<String>(name).ok().flatten().map(|s| s.to_owned());
+        // Prompts cannot be answered from scripts, pipes or CI
+        let interactive = std::io::stdin().is_terminal();
+
+        let owner = match get("owner") {
+            Some(owner) => owner,
+            None => Git::get_git_owner()?,
+        };
+        let repo = match get("repo") {
+            Some(repo) => repo,
+            None => Git::get_git_repo()?,
+        };
+        let token = match get("token") {
+            Some(token) => token,
+            None => Git::get_token(interactive)?,
+        };
+        let ref_name = get("ref").unwrap_or_else(|| Git::get_git_tree_name().unwrap_or_else(|_| "main".to_string()));
+        let api_url = get("api_url")
+            .or_else(|| env::var("GAR_API_URL").ok().filter(|url| !url.trim().is_empty()))
+            .or_else(|| Git::get_git_host().ok().map(|host| GitHub::api_url_for_host(&host)))
+            .unwrap_or_else(|| DEFAULT_API_URL.to_string());
+        let per_page = matches.try_get_one::<u32>("per_page").ok().flatten().copied().unwrap_or(DEFAULT_PER_PAGE);
+        let max_items = matches.try_get_one::<usize>("max_items").ok().flatten().copied();
+        let workflow = get("workflow");
+        let assume_yes = matches.try_get_one::<bool>("yes").ok().flatten().copied().unwrap_or(false);
+        let timeout = matches.try_get_one::<Duration>("timeout").ok().flatten().copied();
+        let output = matches.try_get_one::<OutputFormat>("output").ok().flatten().copied().unwrap_or_default();
 
-        Command {
+        Ok(Command {
             token,
             owner,
             repo,
             ref_name,
+            api_url,
+            per_page,
+            max_items,
+            workflow,
+            assume_yes,
+            interactive,
+            timeout,
+            output,
+        })
+    }
+
+    pub fn github(&self) -> GitHub {
+        GitHub::new(self.token.clone(), self.owner.clone(), self.repo.clone(), self.api_url.clone())
+            .with_pagination(self.per_page, self.max_items)
+            .with_interactive(self.interactive)
+    }
+
+    // `--yes` answers for the user; without a terminal there is nobody to ask, so `action` is refused
+    pub(crate) fn confirm(&self, prompt: &str, action: &str) -> Result<bool, GarError> {
+        self.confirm_or(prompt, action, "--yes")
+    }
+
+    // Like `confirm`, with `flags` named as the way around the refusal
+    pub(crate) fn confirm_or(&self, prompt: &str, action: &str, flags: &str) -> Result<bool, GarError> {
+        if self.assume_yes {
+            return Ok(true);
+        }
+        if !self.interactive {
+            return Err(GarError::Selection(format!("Refusing to {} without confirmation in non-interactive mode; pass {}", action, flags)));
         }
+        let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(prompt)
+            .interact()?;
+        if !confirmed {
+            println!("{}", "Cancel".red());
+        }
+        Ok(confirmed)
     }
 
     pub fn create_arg(name: &'static str, long: &'static str, short: char, help: &'static str) -> Arg {
@@ -29,10 +113,24 @@ impl Command {
             .long(long)
             .short(short)
             .help(help)
-            .exclusive(true)
             .value_parser(value_parser!(String))
     }
 
+    pub fn create_long_arg(name: &'static str, long: &'static str, help: &'static str) -> Arg {
+        Arg::new(name)
+            .long(long)
+            .help(help)
+            .value_parser(value_parser!(String))
+    }
+
+    pub fn create_flag(name: &'static str, long: &'static str, short: char, help: &'static str) -> Arg {
+        Arg::new(name)
+            .long(long)
+            .short(short)
+            .help(help)
+            .action(ArgAction::SetTrue)
+    }
+
     pub fn create_subcommand(name: &'static str, args: &[Arg], about: &'static str) -> CommandClap {
         let mut command = CommandClap::new(name).about(about);
         for arg in args {
@@ -46,25 +144,119 @@ impl Command {
         let owner_arg = Self::create_arg("owner", "owner", 'o', "The owner of the repository where the action is located.");
         let repo_arg = Self::create_arg("repo", "repo", 'p', "The name of the repository where the action is located.");
         let token_arg = Self::create_arg("token", "token", 't', "The token used for authentication. If not provided, the GAR_TOKEN environment variable will be used.");
+        let api_url_arg = Self::create_arg("api_url", "api-url", 'u', "The GitHub API base URL (e.g. https://ghes.example.com/api/v3). If not provided, the GAR_API_URL environment variable or the origin host will be used.");
+        let per_page_arg = Self::create_long_arg("per_page", "per-page", "The number of items requested per page from list endpoints (1-100).")
+            .value_parser(value_parser!(u32).range(1..=100));
+        let max_items_arg = Self::create_long_arg("max_items", "max-items", "The maximum number of items fetched across all pages of a list.")
+            .value_parser(value_parser!(usize));
+        let workflow_arg = Self::create_arg("workflow", "workflow", 'w', "The workflow to use, by name, file name or id. Required in non-interactive mode.");
+        let timeout_arg = Self::create_long_arg("timeout", "timeout", "Stop waiting for a run after this long, e.g. 90s, 30m or 1h30m.")
+            .value_parser(parse_duration);
+        let yes_arg = Self::create_flag("yes", "yes", 'y', "Skip confirmation prompts.");
+        let output_arg = Self::create_long_arg("output", "output", "The format of listings: table, json, csv, markdown or yaml.")
+            .value_parser(|value: &str| value.parse::<OutputFormat>())
+            .default_value("table");
         let inputs_arg = Self::create_arg("inputs", "inputs", 'i', "The name of the event that triggers the action.");
+        let correlation_input_arg = Self::create_long_arg("correlation_input", "correlation-input", "The name of a workflow input that receives a unique id used to find the dispatched run by its run-name.");
 
-        let common_args = vec![ref_arg.clone(), owner_arg.clone(), repo_arg.clone(), token_arg.clone()];
+        let common_args = vec![ref_arg.clone(), owner_arg.clone(), repo_arg.clone(), token_arg.clone(), api_url_arg.clone(), per_page_arg.clone(), max_items_arg.clone(), workflow_arg.clone(), yes_arg.clone(), timeout_arg.clone(), output_arg.clone()];
 
         let mut gar_command = CommandClap::new("gar").bin_name("gar");
         for arg in &common_args {
             gar_command = gar_command.arg(arg.clone());
         }
-        gar_command = gar_command.arg(inputs_arg.clone());
+        let no_wait_arg = Self::create_flag("no_wait", "no-wait", 'n', "Exit right after the dispatch and print the run id instead of waiting for the run to complete.");
+        gar_command = gar_command.arg(inputs_arg.clone()).arg(correlation_input_arg).arg(no_wait_arg);
 
-        let history_command = Self::create_subcommand("history", &common_args, "Shows the history of workflow runs");
-        let details_command = Self::create_subcommand("details", &common_args, "Shows the details of workflow run");
+        // Run filters shared by `history` and `stats`
+        let run_query_args = vec![
+            Self::create_arg("branch", "branch", 'b', "Only include runs of this branch."),
+            Self::create_arg("status", "status", 's', "Only include runs with this status, e.g. queued, in_progress or completed."),
+            Self::create_arg("conclusion", "conclusion", 'c', "Only include runs with this conclusion, e.g. success, failure or cancelled."),
+            Self::create_arg("event", "event", 'e', "Only include runs triggered by this event, e.g. push or workflow_dispatch."),
+            Self::create_arg("actor", "actor", 'a', "Only include runs started by this user."),
+            Self::create_long_arg("created", "created", "Only include runs created in this range: a duration such as 7d, or a GitHub date range such as >=2024-05-01 or 2024-05-01..2024-05-07."),
+            Self::create_flag("all_workflows", "all-workflows", 'A', "Include the runs of every workflow instead of selecting one.").conflicts_with("workflow"),
+        ];
+        let history_command = Self::create_subcommand("history", &common_args, "Shows the history of workflow runs")
+            .args(run_query_args.clone())
+            .arg(Self::create_arg("limit", "limit", 'l', "The number of runs to show (default 10).").value_parser(value_parser!(usize)));
+        let stats_command = Self::create_subcommand("stats", &common_args, "Shows success rate, duration and queue time statistics of recent runs")
+            .args(run_query_args)
+            .arg(Self::create_arg("limit", "limit", 'l', "The number of most recent runs to analyze (default 50).").value_parser(value_parser!(usize)))
+            .arg(Self::create_long_arg("period", "period", "The period of the trend table: day or week.").value_parser(["day", "week"]).default_value("week"))
+            .arg(Self::create_long_arg("concurrency", "concurrency", "The number of runs whose jobs are fetched at the same time (default 4).").value_parser(value_parser!(usize)));
+        let details_command = Self::create_subcommand("details", &common_args, "Shows the details of workflow run")
+            .arg(Self::create_long_arg("run", "run", "The id of the run to show instead of selecting one.").value_parser(value_parser!(u64)))
+            .arg(Self::create_long_arg("job", "job", "Show the logs of the job with this name."))
+            .arg(Self::create_flag("failed", "failed", 'f', "Show the logs of failed jobs only.").conflicts_with("job"))
+            .arg(Self::create_flag("expand_groups", "expand-groups", 'e', "Show the lines inside log groups instead of collapsing them."))
+            .arg(Self::create_long_arg("timestamps", "timestamps", "How to show log timestamps: strip, short (time of day) or full.").value_parser(|value: &str| value.parse::<TimestampMode>()).default_value("strip"))
+            .arg(Self::create_flag("follow", "follow", 'F', "Keep printing new log lines of an in-progress run until it completes."))
+            .arg(Self::create_flag("no_pager", "no-pager", 'P', "Print the logs directly instead of through $PAGER."));
         let autocomplete_command = Self::create_subcommand("autocomplete", &[], "add autocomplete to zsh");
+        let rerun_command = Self::create_subcommand("rerun", &common_args, "Re-runs all, failed or a single job of a workflow run and watches the new attempt")
+            .arg(Self::create_long_arg("run", "run", "The id of the run to re-run instead of selecting one.").value_parser(value_parser!(u64)))
+            .arg(Self::create_flag("failed", "failed", 'f', "Re-run only the failed jobs."))
+            .arg(Self::create_long_arg("job", "job", "The name of a single job to re-run.").conflicts_with("failed"))
+            .arg(Self::create_flag("debug", "debug", 'd', "Enable step debug logging for the new attempt."));
+        let cancel_command = Self::create_subcommand("cancel", &common_args, "Cancels a queued or in-progress workflow run")
+            .arg(Self::create_long_arg("run", "run", "The id of the run to cancel instead of selecting one.").value_parser(value_parser!(u64)))
+            .arg(Self::create_flag("force", "force", 'f', "Force-cancel the run, even if it does not respond to a regular cancellation."));
+        let logs_download_command = Self::create_subcommand("download", &common_args, "Saves the logs of a run as one file per job and step, with an index.json")
+            .arg(Self::create_long_arg("run", "run", "The id of the run to download instead of selecting one.").value_parser(value_parser!(u64)))
+            .arg(Self::create_long_arg("out", "out", "The directory the run-<id> folder is written to.").value_parser(value_parser!(PathBuf)).default_value("."))
+            .arg(Self::create_flag("force", "force", 'f', "Download the logs again even if they are already saved."));
+        let logs_grep_command = Self::create_subcommand("grep", &common_args, "Searches the logs of recent runs of a workflow with a regular expression")
+            .arg(Arg::new("pattern").help("The regular expression to search for.").required(true).value_parser(value_parser!(String)))
+            .arg(Self::create_flag("ignore_case", "ignore-case", 'i', "Match case-insensitively."))
+            .arg(Self::create_arg("branch", "branch", 'b', "Only search runs of this branch."))
+            .arg(Self::create_arg("status", "status", 's', "Only search runs with this status or conclusion, e.g. failure."))
+            .arg(Self::create_arg("limit", "limit", 'l', "The number of most recent runs to search (default 50).").value_parser(value_parser!(usize)))
+            .arg(Self::create_long_arg("concurrency", "concurrency", "The number of archives downloaded at the same time (default 4).").value_parser(value_parser!(usize)))
+            .arg(Self::create_flag("no_cache", "no-cache", 'c', "Do not read or write the local log archive cache."));
+        let logs_command = CommandClap::new("logs")
+            .about("Works with the logs of workflow runs")
+            .subcommand_required(true)
+            .subcommand(logs_download_command)
+            .subcommand(logs_grep_command);
+        let artifacts_command = Self::create_subcommand("artifacts", &common_args, "Lists, downloads and extracts the artifacts of a workflow run")
+            .arg(Self::create_long_arg("run", "run", "The id of the run instead of selecting one.").value_parser(value_parser!(u64)))
+            .arg(Self::create_flag("latest", "latest", 'l', "Use the latest successful run of the workflow on the current branch (or --ref).").conflicts_with("run"))
+            .arg(Self::create_arg("name", "name", 'n', "Download the artifact with this name."))
+            .arg(Self::create_flag("all", "all", 'a', "Download all artifacts of the run.").conflicts_with("name"))
+            .arg(Self::create_flag("list", "list", 'L', "Only list the artifacts.").conflicts_with_all(["name", "all"]))
+            .arg(Self::create_long_arg("out", "out", "The directory artifacts are extracted to, one folder per artifact.").value_parser(value_parser!(PathBuf)).default_value("artifacts"));
+        let older_than_arg = Self::create_long_arg("older_than", "older-than", "Only include items older than this, e.g. 7d or 2w.").value_parser(parse_duration);
+        let larger_than_arg = Self::create_long_arg("larger_than", "larger-than", "Only include items of at least this size, e.g. 500KB or 10MB.").value_parser(parse_size);
+        let dry_run_arg = Self::create_flag("dry_run", "dry-run", 'd', "Only show what would be deleted.");
+        let cleanup_artifacts_command = Self::create_subcommand("artifacts", &common_args, "Deletes artifacts of all runs; --ref filters by branch")
+            .arg(Self::create_arg("name", "name", 'n', "Only include artifacts whose name matches this regular expression."))
+            .arg(older_than_arg.clone())
+            .arg(larger_than_arg.clone())
+            .arg(dry_run_arg.clone());
+        let cleanup_caches_command = Self::create_subcommand("caches", &common_args, "Deletes Actions caches; --ref filters by ref")
+            .arg(Self::create_arg("key", "key", 'k', "Only include caches whose key starts with this prefix."))
+            .arg(older_than_arg.help("Only include caches not accessed for this long, e.g. 7d or 2w."))
+            .arg(larger_than_arg)
+            .arg(dry_run_arg);
+        let cleanup_command = CommandClap::new("cleanup")
+            .about("Frees Actions storage by deleting artifacts or caches")
+            .subcommand_required(true)
+            .subcommand(cleanup_artifacts_command)
+            .subcommand(cleanup_caches_command);
 
         gar_command = gar_command
             .subcommand(history_command)
             .subcommand(details_command)
-            .subcommand(autocomplete_command);
+            .subcommand(autocomplete_command)
+            .subcommand(cancel_command)
+            .subcommand(rerun_command)
+            .subcommand(logs_command)
+            .subcommand(artifacts_command)
+            .subcommand(cleanup_command)
+            .subcommand(stats_command);
 
         gar_command
     }
-}
\ No newline at end of file
+}
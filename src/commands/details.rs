@@ -1,38 +1,243 @@
-use crate::github::GitHub;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::time::Duration;
+use colored::Colorize;
+use dialoguer::Select;
+use dialoguer::theme::ColorfulTheme;
+use serde::Serialize;
+use crate::error::GarError;
+use crate::github::{GitHub, Job, WorkflowRun};
+use crate::log_parser::{LogOptions, LogParser};
+use crate::output::{print_records, print_value, OutputFormat};
+use crate::pager::Pager;
 use super::command::Command;
-use crate::helpers::{unzip_and_concatenate};
+use crate::helpers::{did_you_mean, elapsed_seconds, format_elapsed, unzip_and_concatenate, Interrupts, LogEvent, TempFile};
+
+const FAILED_CONCLUSIONS: [&str; 3] = ["failure", "timed_out", "startup_failure"];
+const FOLLOW_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Serialize)]
+struct RunDetails<'a> {
+    run: &'a WorkflowRun,
+    jobs: &'a [Job],
+}
 
 pub struct DetailsCommand {
     command: Command,
+    run_id: Option<u64>,
+    job: Option<String>,
+    failed: bool,
+    log_options: LogOptions,
+    follow: bool,
+    pager: bool,
 }
 
 impl DetailsCommand {
-    pub fn new(command: Command) -> Self {
-        DetailsCommand { command }
+    pub fn new(command: Command, run_id: Option<u64>, job: Option<String>, failed: bool, log_options: LogOptions, follow: bool, pager: bool) -> Self {
+        DetailsCommand { command, run_id, job, failed, log_options, follow, pager }
     }
 
-    pub async fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let github = GitHub::new(self.command.token.clone(), self.command.owner.clone(), self.command.repo.clone());
-        let workflow = github.select_workflow().await?;
-        let run = github.select_run(workflow.id).await?;
+    pub async fn run(&self) -> Result<(), GarError> {
+        let github = self.command.github();
+        let run = github.resolve_run(self.run_id, self.command.workflow.as_deref(), &[]).await?;
+
+        if !self.command.output.is_table() {
+            return self.print_structured(&github, &run).await;
+        }
 
         println!("ID: {}", run.id);
         println!("Name: {}", run.name);
         println!("Display Title: {}", run.display_title);
         println!("URL: {}", run.html_url);
         println!("Status: {}", run.status);
-        println!("Conclusion: {}", run.conclusion.unwrap_or_else(|| "N/A".to_string()));
+        println!("Conclusion: {}", run.conclusion.clone().unwrap_or_else(|| "N/A".to_string()));
         println!("Branch: {}", run.head_branch);
         println!("Created At: {}", run.created_at);
         println!("Updated At: {}", run.updated_at);
 
-        let logs_data = github.github_request_bytes(run.logs_url.as_str(), "GET", None, Some("application/vnd.github+json")).await?;
-        let logs = unzip_and_concatenate(logs_data.clone());
-        println!("Logs: \n{}", logs.unwrap());
+        let jobs = github.get_run_jobs(run.id).await?;
+        println!("Jobs:");
+        for job in &jobs {
+            println!("  {} - {} {}", job.name, Self::job_state(job), format_elapsed(&job.started_at, &job.completed_at).dimmed());
+        }
+
+        let selected = self.select_jobs(&jobs)?;
+        if self.follow {
+            let job_ids = selected.map(|selected| selected.iter().map(|job| job.id).collect());
+            return self.follow_logs(&github, &run, job_ids).await;
+        }
+        match selected {
+            Some(selected) => {
+                if selected.is_empty() {
+                    println!("{}", "No failed jobs in this run".green());
+                    return Ok(());
+                }
+                let mut parser = LogParser::new(self.log_options);
+                let mut out = Pager::start(self.pager);
+                for job in selected {
+                    let logs = github.get_job_logs(job.id).await?;
+                    parser.set_step(&job.name, 0, &mut out)?;
+                    writeln!(out, "{}", format!("-------------- {} --------------", job.name).bold())?;
+                    for line in logs.lines() {
+                        parser.feed(line, &mut out)?;
+                    }
+                }
+                parser.finish(&mut out)?;
+                out.finish()?;
+            }
+            None => self.print_archive(&github, &run).await?,
+        }
+
+        Ok(())
+    }
+
+    // Prints the lines added to the job logs since the last poll until the run completes.
+    // None follows every job of the run
+    async fn follow_logs(&self, github: &GitHub, run: &WorkflowRun, job_ids: Option<Vec<u64>>) -> Result<(), GarError> {
+        let mut parser = LogParser::new(self.log_options);
+        let mut out = std::io::stdout();
+        let mut printed: HashMap<u64, usize> = HashMap::new();
+        let mut finished: HashSet<u64> = HashSet::new();
+        let mut current = None;
+
+        println!("{}", "Following the job logs, press Ctrl-C to stop".dimmed());
+        // One listener for the whole loop, so a Ctrl-C during a request or a retry backoff is not lost
+        let mut interrupts = Interrupts::new()?;
+        loop {
+            let pass = async {
+                // Checked before reading the logs, so the last pass sees their final content
+                let run_completed = github.get_workflow_run(run.id).await?.status == "completed";
+                let jobs = github.get_run_jobs(run.id).await?;
+
+                for job in jobs.iter().filter(|job| job_ids.as_ref().is_none_or(|ids| ids.contains(&job.id))) {
+                    if finished.contains(&job.id) || job.started_at.is_none() {
+                        continue;
+                    }
+                    // Logs of a job that has not produced output yet are not available
+                    let Ok(logs) = github.get_job_logs(job.id).await else {
+                        continue;
+                    };
+                    let job_completed = job.status == "completed";
+                    let mut lines: Vec<&str> = logs.lines().collect();
+                    // A line without its newline may still be written to
+                    if !job_completed && !logs.ends_with('\n') {
+                        lines.pop();
+                    }
+
+                    let seen = printed.entry(job.id).or_default();
+                    if lines.len() > *seen {
+                        if current != Some(job.id) {
+                            parser.set_step(&job.name, *seen, &mut out)?;
+                            writeln!(out, "{}", format!("-------------- {} --------------", job.name).bold())?;
+                            current = Some(job.id);
+                        }
+                        for line in &lines[*seen..] {
+                            parser.feed(line, &mut out)?;
+                        }
+                        *seen = lines.len();
+                    }
+                    if job_completed {
+                        finished.insert(job.id);
+                    }
+                }
+
+                if !run_completed {
+                    tokio::time::sleep(github.poll_interval(FOLLOW_INTERVAL)).await;
+                }
+                Ok::<bool, GarError>(run_completed)
+            };
+            let run_completed = tokio::select! {
+                run_completed = pass => run_completed?,
+                _ = interrupts.recv() => {
+                    println!();
+                    break;
+                }
+            };
+            if run_completed {
+                break;
+            }
+        }
 
-        // https://api.github.com/repos/s00d/github-action-runner/actions/runs/7090586915/logs
-        // https://api.github.com/repos/s00d/github-action-runner/actions/runs/7090586915/logs
+        parser.finish(&mut out)?;
+        Ok(())
+    }
+
+    // Run and jobs for scripts; JSON and YAML carry the run with its jobs, CSV and Markdown list the jobs
+    async fn print_structured(&self, github: &GitHub, run: &WorkflowRun) -> Result<(), GarError> {
+        let jobs = github.get_run_jobs(run.id).await?;
+        let output = self.command.output;
+        if matches!(output, OutputFormat::Json | OutputFormat::Yaml) {
+            return print_value(output, &RunDetails { run, jobs: &jobs });
+        }
+
+        let rows: Vec<Vec<String>> = jobs.iter().map(|job| vec![
+            job.id.to_string(),
+            job.name.clone(),
+            job.status.clone(),
+            job.conclusion.clone().unwrap_or_else(|| "N/A".to_string()),
+            job.started_at.clone().unwrap_or_default(),
+            job.completed_at.clone().unwrap_or_default(),
+            elapsed_seconds(&job.started_at, &job.completed_at).map(|seconds| seconds.to_string()).unwrap_or_default(),
+        ]).collect();
+        print_records(output, &jobs, &["ID", "Job", "Status", "Conclusion", "Started At", "Completed At", "Duration (s)"], &rows)
+    }
+
+    fn job_state(job: &Job) -> String {
+        let state = job.conclusion.clone().unwrap_or_else(|| job.status.clone());
+        match state.as_str() {
+            "success" => state.green().to_string(),
+            "failure" | "timed_out" | "startup_failure" => state.red().to_string(),
+            "in_progress" | "queued" => state.yellow().to_string(),
+            _ => state,
+        }
+    }
 
+    // None stands for the whole run log archive
+    fn select_jobs<'a>(&self, jobs: &'a [Job]) -> Result<Option<Vec<&'a Job>>, GarError> {
+        if self.failed {
+            let failed = jobs.iter()
+                .filter(|job| job.conclusion.as_deref().is_some_and(|c| FAILED_CONCLUSIONS.contains(&c)))
+                .collect();
+            return Ok(Some(failed));
+        }
+        if let Some(name) = &self.job {
+            return match jobs.iter().find(|job| &job.name == name) {
+                Some(job) => Ok(Some(vec![job])),
+                None => {
+                    let message = did_you_mean(format!("The run has no job named \"{}\"", name), name, jobs.iter().map(|job| job.name.as_str()));
+                    Err(GarError::Selection(message))
+                }
+            };
+        }
+        if !self.command.interactive || jobs.is_empty() {
+            return Ok(None);
+        }
+
+        let mut items: Vec<String> = jobs.iter().map(|job| format!("{} - {}", job.name, Self::job_state(job))).collect();
+        items.push("Full run log archive".to_string());
+        let selected = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt("Show logs of:")
+            .items(&items)
+            .default(0)
+            .interact()?;
+
+        Ok(jobs.get(selected).map(|job| vec![job]))
+    }
+
+    async fn print_archive(&self, github: &GitHub, run: &WorkflowRun) -> Result<(), GarError> {
+        // The archive is spooled to disk, it can be hundreds of megabytes
+        let (_temp, mut archive) = TempFile::create(&format!("run-{}", run.id))?;
+        github.github_request_to_file(run.logs_url.as_str(), Some("application/vnd.github+json"), &mut archive).await?;
+
+        let mut parser = LogParser::new(self.log_options);
+        let mut out = Pager::start(self.pager);
+        writeln!(out, "Logs:")?;
+        unzip_and_concatenate(archive, |event| match event {
+            LogEvent::Entry(file_name) => parser.start_file(file_name, &mut out),
+            LogEvent::Line(line) => parser.feed(line, &mut out),
+        })?;
+        parser.finish(&mut out)?;
+        out.finish()?;
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -1,45 +1,151 @@
-use prettytable::{format, row, Cell, Row, Table};
-use crate::github::GitHub;
+use chrono::{SecondsFormat, Utc};
+use clap::ArgMatches;
+use crate::error::GarError;
+use crate::github::{GitHub, RunFilter, WorkflowRun};
+use crate::helpers::parse_duration;
+use crate::output::print_records;
 use super::command::Command;
 
+const DEFAULT_HISTORY_LIMIT: usize = 10;
+
+// Which runs to look at, shared by `history` and `stats`
+pub(crate) struct RunQuery {
+    pub(crate) branch: Option<String>,
+    pub(crate) status: Option<String>,
+    pub(crate) conclusion: Option<String>,
+    pub(crate) event: Option<String>,
+    pub(crate) actor: Option<String>,
+    pub(crate) created: Option<String>,
+    pub(crate) limit: Option<usize>,
+    pub(crate) all_workflows: bool,
+}
+
+impl RunQuery {
+    pub(crate) fn from_matches(matches: &ArgMatches) -> RunQuery {
+        RunQuery {
+            branch: matches.get_one::<String>("branch").cloned(),
+            status: matches.get_one::<String>("status").cloned(),
+            conclusion: matches.get_one::<String>("conclusion").cloned(),
+            event: matches.get_one::<String>("event").cloned(),
+            actor: matches.get_one::<String>("actor").cloned(),
+            created: matches.get_one::<String>("created").cloned(),
+            limit: matches.get_one::<usize>("limit").copied(),
+            all_workflows: matches.get_flag("all_workflows"),
+        }
+    }
+
+    pub(crate) async fn fetch(&self, command: &Command, github: &GitHub, default_limit: usize) -> Result<Vec<WorkflowRun>, GarError> {
+        let filter = self.filter()?;
+        let limit = self.limit.or(command.max_items).unwrap_or(default_limit);
+        let mut runs = if self.all_workflows {
+            github.get_repo_runs(&filter, Some(limit)).await?
+        } else {
+            let workflow = github.select_workflow(command.workflow.as_deref()).await?;
+            github.get_workflow_runs(workflow.id, &filter, Some(limit)).await?
+        };
+        if let (Some(status), Some(_)) = (&self.status, &self.conclusion) {
+            runs.retain(|run| &run.status == status);
+        }
+        Ok(runs)
+    }
+
+    // The API has a single `status` parameter that also accepts conclusions. When both are given the
+    // conclusion goes to the API, so `limit` counts matching runs; it implies `completed`, which leaves
+    // nothing to check locally but the status itself
+    fn filter(&self) -> Result<RunFilter, GarError> {
+        Ok(RunFilter {
+            branch: self.branch.clone(),
+            event: self.event.clone(),
+            actor: self.actor.clone(),
+            status: self.conclusion.clone().or_else(|| self.status.clone()),
+            created: self.created.as_deref().map(Self::created_range).transpose()?,
+        })
+    }
+
+    // A duration such as "7d" means "created within the last 7 days", anything else is passed on as a
+    // GitHub date range such as ">=2024-05-01" or "2024-05-01..2024-05-07"
+    fn created_range(created: &str) -> Result<String, GarError> {
+        let is_duration = created.chars().all(|c| c.is_ascii_digit() || "smhdw".contains(c));
+        match parse_duration(created) {
+            Ok(duration) => {
+                let since = i64::try_from(duration.as_secs()).ok()
+                    .and_then(chrono::Duration::try_seconds)
+                    .and_then(|duration| Utc::now().checked_sub_signed(duration))
+                    .ok_or_else(|| GarError::Selection(format!("--created {} reaches back further than dates go", created)))?;
+                Ok(format!(">={}", since.to_rfc3339_opts(SecondsFormat::Secs, true)))
+            }
+            Err(e) if is_duration => Err(GarError::Selection(format!("Invalid --created value: {}", e))),
+            Err(_) if created.chars().next().is_some_and(|c| c.is_ascii_digit() || "<>*".contains(c)) => Ok(created.to_string()),
+            Err(_) => Err(GarError::Selection(format!("Invalid --created value \"{}\", expected e.g. 7d, >=2024-05-01 or 2024-05-01..2024-05-07", created))),
+        }
+    }
+}
+
 pub struct HistoryCommand {
     command: Command,
+    options: RunQuery,
 }
 
 impl HistoryCommand {
-    pub fn new(command: Command) -> Self {
-        HistoryCommand { command }
+    pub fn new(command: Command, options: RunQuery) -> Self {
+        HistoryCommand { command, options }
     }
 
-    pub async fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let github = GitHub::new(self.command.token.clone(), self.command.owner.clone(), self.command.repo.clone());
-
-        let workflow = github.select_workflow().await?;
-        let runs = github.get_workflow_runs(workflow.id).await?;
+    pub async fn run(&self) -> Result<(), GarError> {
+        let github = self.command.github();
+        let runs = self.options.fetch(&self.command, &github, DEFAULT_HISTORY_LIMIT).await?;
 
-        let mut table = Table::new();
-        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
-        table.add_row(row!["ID", "Branch", "Status", "Conclusion", "Created At", "Updated At", "Url"]);
+        let mut headers = vec!["ID"];
+        if self.options.all_workflows {
+            headers.push("Workflow");
+        }
+        headers.extend(["Branch", "Event", "Status", "Conclusion", "Created At", "Updated At", "Url"]);
 
-        for run in runs.iter().take(10) {
+        let rows: Vec<Vec<String>> = runs.iter().map(|run| {
             let conclusion = match &run.conclusion {
-                Some(value) => value.as_str(),
-                None => "N/A",
+                Some(value) => value.clone(),
+                None => "N/A".to_string(),
             };
 
-            table.add_row(Row::new(vec![
-                Cell::new(&run.id.to_string()),
-                Cell::new(&run.head_branch),
-                Cell::new(&run.status),
-                Cell::new(conclusion),
-                Cell::new(&run.created_at),
-                Cell::new(&run.updated_at),
-                Cell::new(&run.html_url),
-            ]));
-        }
+            let mut row = vec![run.id.to_string()];
+            if self.options.all_workflows {
+                row.push(run.name.clone());
+            }
+            row.extend([
+                run.head_branch.clone(),
+                run.event.clone(),
+                run.status.clone(),
+                conclusion,
+                run.created_at.clone(),
+                run.updated_at.clone(),
+                run.html_url.clone(),
+            ]);
+            row
+        }).collect();
 
-        table.printstd();
+        print_records(self.command.output, &runs, &headers, &rows)?;
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RunQuery;
+
+    #[test]
+    fn turns_durations_into_ranges() {
+        assert!(RunQuery::created_range("7d").unwrap().starts_with(">="));
+        assert!(RunQuery::created_range("90").unwrap().starts_with(">="));
+        for range in [">=2024-05-01", "2024-05-01..2024-05-07", "<2024-05-01T10:00:00Z", "*..2024-05-01"] {
+            assert_eq!(RunQuery::created_range(range).unwrap(), range);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_and_out_of_range_values() {
+        for created in ["yesterday", "9999999999999999", "300000000000000d", "0d"] {
+            assert!(RunQuery::created_range(created).is_err(), "{}", created);
+        }
+    }
+}
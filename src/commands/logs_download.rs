@@ -0,0 +1,144 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use crate::error::GarError;
+use crate::github::{Job, WorkflowRun};
+use crate::helpers::{elapsed_seconds, extract_archive, TempFile};
+use crate::log_parser::strip_number;
+use super::command::Command;
+
+const INDEX_FILE: &str = "index.json";
+
+#[derive(Serialize, Deserialize)]
+struct LogIndex {
+    run_id: u64,
+    run_attempt: u64,
+    name: String,
+    html_url: String,
+    status: String,
+    conclusion: Option<String>,
+    head_branch: String,
+    created_at: String,
+    jobs: Vec<JobIndex>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JobIndex {
+    id: u64,
+    name: String,
+    conclusion: Option<String>,
+    duration_seconds: Option<i64>,
+    log_file: Option<PathBuf>,
+    steps: Vec<StepIndex>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StepIndex {
+    number: u64,
+    name: String,
+    conclusion: Option<String>,
+    duration_seconds: Option<i64>,
+    log_file: Option<PathBuf>,
+}
+
+pub struct LogsDownloadCommand {
+    command: Command,
+    run_id: Option<u64>,
+    out: PathBuf,
+    force: bool,
+}
+
+impl LogsDownloadCommand {
+    pub fn new(command: Command, run_id: Option<u64>, out: PathBuf, force: bool) -> Self {
+        LogsDownloadCommand { command, run_id, out, force }
+    }
+
+    pub async fn run(&self) -> Result<(), GarError> {
+        let github = self.command.github();
+        let run = github.resolve_run(self.run_id, self.command.workflow.as_deref(), &["completed"]).await?;
+
+        let dir = self.out.join(format!("run-{}", run.id));
+        if !self.force && Self::is_downloaded(&dir, &run) {
+            println!("{}", format!("Logs of run {} (attempt {}) are already in {}, pass --force to download them again", run.id, run.run_attempt, dir.display()).yellow());
+            return Ok(());
+        }
+        if run.status != "completed" {
+            println!("{}", format!("Run {} is still {}, the logs will be incomplete", run.id, run.status).yellow());
+        }
+
+        let (_temp, mut archive) = TempFile::create_in(&self.out, &format!("run-{}", run.id))?;
+        github.github_request_to_file(run.logs_url.as_str(), Some("application/vnd.github+json"), &mut archive).await?;
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+        let files = extract_archive(archive, &dir)?;
+
+        let jobs = github.get_run_jobs(run.id).await?;
+        let index = Self::build_index(&run, &jobs, &files);
+        // Written last, so an interrupted download is not mistaken for a complete one
+        fs::write(dir.join(INDEX_FILE), serde_json::to_string_pretty(&index)?)?;
+
+        println!("Saved {} log files of run {} to {}", files.len(), run.id, dir.display());
+        Ok(())
+    }
+
+    fn is_downloaded(dir: &Path, run: &WorkflowRun) -> bool {
+        let Ok(contents) = fs::read_to_string(dir.join(INDEX_FILE)) else {
+            return false;
+        };
+        // A new attempt or a run that was still in progress has to be downloaded again
+        serde_json::from_str::<LogIndex>(&contents)
+            .is_ok_and(|index| index.run_attempt == run.run_attempt && index.status == "completed" && run.status == "completed")
+    }
+
+    fn build_index(run: &WorkflowRun, jobs: &[Job], files: &[PathBuf]) -> LogIndex {
+        let jobs = jobs.iter().map(|job| {
+            let job_name = Self::normalize(&job.name);
+            // Archives hold "<n>_<job>.txt" with the whole job log and "<job>/<n>_<step>.txt" per step
+            let log_file = files.iter()
+                .find(|file| file.parent().is_some_and(|p| p.as_os_str().is_empty()) && file.file_name().is_some_and(|name| Self::normalize(strip_number(&name.to_string_lossy())) == job_name))
+                .cloned();
+            let steps = job.steps.iter().map(|step| StepIndex {
+                number: step.number,
+                name: step.name.clone(),
+                conclusion: step.conclusion.clone(),
+                duration_seconds: elapsed_seconds(&step.started_at, &step.completed_at),
+                log_file: files.iter()
+                    .find(|file| {
+                        file.parent().is_some_and(|p| Self::normalize(&p.to_string_lossy()) == job_name)
+                            && file.file_name().is_some_and(|n| n.to_string_lossy().starts_with(&format!("{}_", step.number)))
+                    })
+                    .cloned(),
+            }).collect();
+
+            JobIndex {
+                id: job.id,
+                name: job.name.clone(),
+                conclusion: job.conclusion.clone(),
+                duration_seconds: elapsed_seconds(&job.started_at, &job.completed_at),
+                log_file,
+                steps,
+            }
+        }).collect();
+
+        LogIndex {
+            run_id: run.id,
+            run_attempt: run.run_attempt,
+            name: run.name.clone(),
+            html_url: run.html_url.clone(),
+            status: run.status.clone(),
+            conclusion: run.conclusion.clone(),
+            head_branch: run.head_branch.clone(),
+            created_at: run.created_at.clone(),
+            jobs,
+        }
+    }
+
+    // "3_build (ubuntu).txt" -> "build (ubuntu)"
+    // GitHub drops characters such as '/' and ':' from job names when naming archive entries
+    fn normalize(name: &str) -> String {
+        name.chars().filter(|c| c.is_alphanumeric()).flat_map(|c| c.to_lowercase()).collect()
+    }
+}
@@ -0,0 +1,212 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use colored::Colorize;
+use regex::{Regex, RegexBuilder};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use zip::ZipArchive;
+use crate::error::GarError;
+use crate::github::{GitHub, RunFilter, WorkflowRun};
+use crate::helpers::{for_each_line_lossy, TempFile};
+use crate::log_parser::{split_timestamp, strip_number};
+use super::command::Command;
+
+const DEFAULT_GREP_LIMIT: usize = 50;
+const DEFAULT_CONCURRENCY: usize = 4;
+// Cached archives are deleted this long after they were downloaded, so the cache does not grow without bound
+const CACHE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+pub(crate) struct GrepOptions {
+    pub(crate) pattern: String,
+    pub(crate) ignore_case: bool,
+    pub(crate) branch: Option<String>,
+    pub(crate) status: Option<String>,
+    pub(crate) limit: Option<usize>,
+    pub(crate) concurrency: Option<usize>,
+    pub(crate) no_cache: bool,
+}
+
+struct LogMatch {
+    job: String,
+    step: String,
+    line_number: usize,
+    line: String,
+}
+
+pub struct LogsGrepCommand {
+    command: Command,
+    options: GrepOptions,
+}
+
+impl LogsGrepCommand {
+    pub fn new(command: Command, options: GrepOptions) -> Self {
+        LogsGrepCommand { command, options }
+    }
+
+    pub async fn run(&self) -> Result<(), GarError> {
+        let regex = RegexBuilder::new(&self.options.pattern)
+            .case_insensitive(self.options.ignore_case)
+            .build()
+            .map_err(|e| GarError::Selection(format!("Invalid pattern: {}", e)))?;
+
+        let github = Arc::new(self.command.github());
+        let workflow = github.select_workflow(self.command.workflow.as_deref()).await?;
+        let filter = RunFilter {
+            branch: self.options.branch.clone(),
+            status: self.options.status.clone(),
+            ..RunFilter::default()
+        };
+        let runs = github.get_workflow_runs(workflow.id, &filter, Some(self.options.limit.unwrap_or(DEFAULT_GREP_LIMIT))).await?;
+        if runs.is_empty() {
+            return Err(GarError::Selection("No matching runs found for the workflow".to_string()));
+        }
+        println!("Searching the logs of {} runs of \"{}\"...", runs.len(), workflow.name);
+
+        let cache_dir = if self.options.no_cache { None } else { self.cache_dir() };
+        if let Some(cache_dir) = &cache_dir {
+            Self::prune_cache(cache_dir);
+        }
+        let semaphore = Arc::new(Semaphore::new(self.options.concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1)));
+        let mut tasks = JoinSet::new();
+        for (position, run) in runs.iter().cloned().enumerate() {
+            let github = github.clone();
+            let semaphore = semaphore.clone();
+            let regex = regex.clone();
+            let cache_dir = cache_dir.clone();
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                let result = match Self::load_archive(&github, &run, cache_dir).await {
+                    // Scanning is blocking work, keep it off the runtime's worker threads
+                    Ok((archive, temp)) => tokio::task::spawn_blocking(move || {
+                        let result = Self::scan_archive(archive, &regex);
+                        drop(temp);
+                        result
+                    }).await.unwrap_or_else(|e| Err(GarError::Other(e.to_string()))),
+                    Err(e) => Err(e),
+                };
+                (position, run, result)
+            });
+        }
+
+        let mut results = Vec::new();
+        while let Some(joined) = tasks.join_next().await {
+            results.push(joined.map_err(|e| GarError::Other(e.to_string()))?);
+        }
+        results.sort_by_key(|(position, _, _)| *position);
+
+        let (mut total, mut matched_runs) = (0, 0);
+        for (_, run, result) in results {
+            let matches = match result {
+                Ok(matches) => matches,
+                // Logs expire with the repository's retention period; one missing archive should not end the search
+                Err(e) => {
+                    println!("{}", format!("Skipping run {}: {}", run.id, e).yellow());
+                    continue;
+                }
+            };
+            if matches.is_empty() {
+                continue;
+            }
+            matched_runs += 1;
+            total += matches.len();
+            println!("{}", format!("Run {} ({}, {}) {}", run.id, run.head_branch, run.conclusion.as_deref().unwrap_or(&run.status), run.html_url).bold());
+            for found in matches {
+                println!("  {} {} {}", format!("{} / {}", found.job, found.step).cyan(), format!("{}:", found.line_number).dimmed(), Self::highlight(&regex, &found.line));
+            }
+        }
+
+        println!("{} matching lines in {} of {} runs", total, matched_runs, runs.len());
+        Ok(())
+    }
+
+    // ~/.cache/gar/logs/<owner>/<repo>, or None when there is no cache directory
+    fn cache_dir(&self) -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("gar").join("logs").join(&self.command.owner).join(&self.command.repo))
+    }
+
+    // Removes archives older than CACHE_MAX_AGE, along with downloads a crash left behind. Failures are
+    // ignored: the worst outcome is a cache that is larger than it needs to be
+    fn prune_cache(cache_dir: &Path) {
+        let Ok(entries) = fs::read_dir(cache_dir) else {
+            return;
+        };
+        let now = SystemTime::now();
+        for entry in entries.flatten() {
+            let expired = entry.metadata().ok()
+                .filter(|metadata| metadata.is_file())
+                .and_then(|metadata| metadata.modified().ok())
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_some_and(|age| age > CACHE_MAX_AGE);
+            if expired {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    // Archives are spooled to disk rather than held in memory, in the cache directory when there is one.
+    // The returned guard removes an uncached download once the file is dropped
+    async fn load_archive(github: &GitHub, run: &WorkflowRun, cache_dir: Option<PathBuf>) -> Result<(fs::File, Option<TempFile>), GarError> {
+        // Archives only change while a run is in progress or when a new attempt starts
+        let cache_dir = cache_dir.filter(|_| run.status == "completed");
+        let cache_file = cache_dir.as_ref().map(|dir| dir.join(format!("{}-{}.zip", run.id, run.run_attempt)));
+        if let Some(file) = cache_file.as_ref().and_then(|file| fs::File::open(file).ok()) {
+            return Ok((file, None));
+        }
+
+        let name = format!("run-{}", run.id);
+        let (temp, mut file) = match &cache_dir {
+            Some(dir) => TempFile::create_in(dir, &name).or_else(|_| TempFile::create(&name))?,
+            None => TempFile::create(&name)?,
+        };
+        github.github_request_to_file(run.logs_url.as_str(), Some("application/vnd.github+json"), &mut file).await?;
+        // A failed cache write only costs a download next time
+        if let Some(cache_file) = cache_file {
+            if fs::rename(&temp.path, &cache_file).is_ok() {
+                return Ok((file, None));
+            }
+        }
+        Ok((file, Some(temp)))
+    }
+
+    // Searches the per-step files, or the per-job files of archives that have no step folders
+    fn scan_archive(archive: fs::File, regex: &Regex) -> Result<Vec<LogMatch>, GarError> {
+        let mut archive = ZipArchive::new(archive)?;
+        let has_steps = archive.file_names().any(|name| name.contains('/'));
+
+        let mut matches = Vec::new();
+        for i in 0..archive.len() {
+            let file = archive.by_index(i)?;
+            let name = file.name().to_string();
+            if file.is_dir() || name.contains('/') != has_steps {
+                continue;
+            }
+            let (job, step) = match name.rsplit_once('/') {
+                Some((job, file_name)) => (job.to_string(), strip_number(file_name).to_string()),
+                None => (strip_number(&name).to_string(), String::new()),
+            };
+
+            for_each_line_lossy(file, |line_number, line| {
+                let (_, line) = split_timestamp(line);
+                if regex.is_match(line) {
+                    matches.push(LogMatch { job: job.clone(), step: step.clone(), line_number, line: line.to_string() });
+                }
+                Ok(())
+            }).map_err(|e| GarError::Archive(format!("{}: {}", name, e)))?;
+        }
+        Ok(matches)
+    }
+
+    fn highlight(regex: &Regex, line: &str) -> String {
+        let mut highlighted = String::new();
+        let mut last = 0;
+        for found in regex.find_iter(line) {
+            highlighted.push_str(&line[last..found.start()]);
+            highlighted.push_str(&found.as_str().red().bold().to_string());
+            last = found.end();
+        }
+        highlighted.push_str(&line[last..]);
+        highlighted
+    }
+}
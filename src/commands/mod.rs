@@ -2,4 +2,11 @@ pub(crate) mod command;
 pub(crate) mod history;
 pub(crate) mod details;
 pub(crate) mod autocomplete;
-pub(crate) mod run_workflow;
\ No newline at end of file
+pub(crate) mod run_workflow;
+pub(crate) mod cancel;
+pub(crate) mod rerun;
+pub(crate) mod logs_download;
+pub(crate) mod logs_grep;
+pub(crate) mod artifacts;
+pub(crate) mod cleanup;
+pub(crate) mod stats;
\ No newline at end of file
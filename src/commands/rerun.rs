@@ -0,0 +1,132 @@
+use std::time::{Duration, Instant};
+use colored::Colorize;
+use dialoguer::Select;
+use dialoguer::theme::ColorfulTheme;
+use crate::error::GarError;
+use crate::github::{GitHub, Job, WorkflowRun};
+use crate::helpers::did_you_mean;
+use crate::watch::{conclusion_result, wait_for_run};
+use super::command::Command;
+
+// How long GitHub may take to register the new attempt
+const ATTEMPT_LOOKUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+enum RerunMode {
+    All,
+    Failed,
+    Job(Job),
+}
+
+pub struct RerunCommand {
+    command: Command,
+    run_id: Option<u64>,
+    failed: bool,
+    job: Option<String>,
+    debug: bool,
+}
+
+impl RerunCommand {
+    pub fn new(command: Command, run_id: Option<u64>, failed: bool, job: Option<String>, debug: bool) -> Self {
+        RerunCommand { command, run_id, failed, job, debug }
+    }
+
+    pub async fn run(&self) -> Result<(), GarError> {
+        let github = self.command.github();
+
+        let run = github.resolve_run(self.run_id, self.command.workflow.as_deref(), &[]).await?;
+        if run.status != "completed" {
+            return Err(GarError::Selection(format!("Run {} is still {}; only completed runs can be re-run", run.id, run.status)));
+        }
+
+        let mode = self.select_mode(&github, &run).await?;
+        let description = match &mode {
+            RerunMode::All => "all jobs".to_string(),
+            RerunMode::Failed => "failed jobs".to_string(),
+            RerunMode::Job(job) => format!("job \"{}\"", job.name),
+        };
+
+        let prompt = format!("Re-run {} of \"{}\" run {}{}?", description, run.name, run.id, if self.debug { " with debug logging" } else { "" });
+        if !self.command.confirm(&prompt, &format!("re-run run {}", run.id))? {
+            return Ok(());
+        }
+
+        match &mode {
+            RerunMode::All => github.rerun_run(run.id, false, self.debug).await?,
+            RerunMode::Failed => github.rerun_run(run.id, true, self.debug).await?,
+            RerunMode::Job(job) => github.rerun_job(job.id, self.debug).await?,
+        }
+        println!("Re-run of {} requested.", description);
+
+        let attempt = Self::wait_for_new_attempt(&github, &run).await?;
+        println!("Action: {} (attempt {})", attempt.html_url, attempt.run_attempt);
+
+        let run = wait_for_run(&github, run.id, self.command.timeout).await?;
+        conclusion_result(&run)
+    }
+
+    async fn select_mode(&self, github: &GitHub, run: &WorkflowRun) -> Result<RerunMode, GarError> {
+        if let Some(name) = &self.job {
+            let jobs = github.get_run_jobs(run.id).await?;
+            return match jobs.iter().find(|job| &job.name == name) {
+                Some(job) => Ok(RerunMode::Job(job.clone())),
+                None => {
+                    let message = did_you_mean(format!("Run {} has no job named \"{}\"", run.id, name), name, jobs.iter().map(|job| job.name.as_str()));
+                    Err(GarError::Selection(message))
+                }
+            };
+        }
+        if self.failed {
+            return Ok(RerunMode::Failed);
+        }
+        if !self.command.interactive {
+            return Ok(RerunMode::All);
+        }
+
+        let choices = ["Re-run all jobs", "Re-run failed jobs", "Re-run a single job"];
+        let selected = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt("What should be re-run?")
+            .items(&choices)
+            .default(if run.conclusion.as_deref() == Some("success") { 0 } else { 1 })
+            .interact()?;
+
+        match selected {
+            0 => Ok(RerunMode::All),
+            1 => Ok(RerunMode::Failed),
+            _ => {
+                let jobs = github.get_run_jobs(run.id).await?;
+                if jobs.is_empty() {
+                    return Err(GarError::Selection(format!("Run {} has no jobs", run.id)));
+                }
+                let names: Vec<String> = jobs.iter().map(|job| {
+                    let conclusion = job.conclusion.clone().unwrap_or_else(|| job.status.clone());
+                    match conclusion.as_str() {
+                        "success" => format!("{} - {}", job.name, conclusion).green().to_string(),
+                        "failure" | "timed_out" => format!("{} - {}", job.name, conclusion).red().to_string(),
+                        _ => format!("{} - {}", job.name, conclusion).white().to_string(),
+                    }
+                }).collect();
+                let selected = Select::with_theme(&ColorfulTheme::default())
+                    .with_prompt("Select a job:")
+                    .items(&names)
+                    .default(0)
+                    .interact()?;
+                Ok(RerunMode::Job(jobs[selected].clone()))
+            }
+        }
+    }
+
+    // Until GitHub registers the new attempt the run still reports the previous, completed one
+    async fn wait_for_new_attempt(github: &GitHub, run: &WorkflowRun) -> Result<WorkflowRun, GarError> {
+        let started = Instant::now();
+        loop {
+            let current = github.get_workflow_run(run.id).await?;
+            if current.run_attempt > run.run_attempt || current.status != "completed" {
+                return Ok(current);
+            }
+            if started.elapsed() >= ATTEMPT_LOOKUP_TIMEOUT {
+                return Err(GarError::Timeout(format!("No new attempt of run {} appeared within {}s", run.id, ATTEMPT_LOOKUP_TIMEOUT.as_secs())));
+            }
+            tokio::time::sleep(github.poll_interval(Duration::from_secs(2))).await;
+        }
+    }
+}
@@ -1,22 +1,29 @@
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
 use colored::Colorize;
-use dialoguer::Confirm;
-use dialoguer::theme::ColorfulTheme;
-use indicatif::{ProgressBar, ProgressStyle};
 use serde_json::json;
-use tokio::sync::Mutex;
-use crate::github::GitHub;
-use crate::helpers::{beep, update_progress_bar};
+use crate::error::GarError;
+use crate::github::{GitHub, RunFilter, Workflow, WorkflowRun};
+use crate::helpers::beep;
+use crate::watch::{conclusion_result, wait_for_run};
+use crate::workflow::{load_dispatch_inputs, prompt_inputs, validate_inputs};
 use super::command::Command;
 
+// How long to look for the run created by a dispatch before giving up
+const RUN_LOOKUP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+// Tolerated difference between the local clock and GitHub's
+const CLOCK_SKEW_SECONDS: i64 = 10;
+
 pub struct RunWorkflowCommand {
     command: Command,
     inputs: HashMap<String, String>,
+    correlation_input: Option<String>,
+    no_wait: bool,
 }
 
 impl RunWorkflowCommand {
-    pub fn new(command: Command, inputs: String) -> Self {
+    pub fn new(command: Command, inputs: String, correlation_input: Option<String>, no_wait: bool) -> Self {
         let inputs_collect: HashMap<_, _> = inputs
             .split('&')
             .filter_map(|pair| {
@@ -29,72 +36,126 @@ impl RunWorkflowCommand {
             })
             .collect();
 
-        RunWorkflowCommand { command, inputs: inputs_collect }
+        RunWorkflowCommand { command, inputs: inputs_collect, correlation_input, no_wait }
     }
 
-    pub async fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let github = GitHub::new(self.command.token.clone(), self.command.owner.clone(), self.command.repo.clone());
+    pub async fn run(&self) -> Result<(), GarError> {
+        let github = self.command.github();
+
+        let workflow = github.select_workflow(self.command.workflow.as_deref()).await?;
+
+        let mut provided = self.inputs.clone();
+        let correlation_id = self.correlation_input.as_ref().map(|name| {
+            let id = Self::generate_correlation_id();
+            provided.insert(name.clone(), id.clone());
+            id
+        });
 
-        let workflow = github.select_workflow().await?;
+        let inputs = match load_dispatch_inputs(&github, &workflow, &self.command.ref_name).await {
+            Ok(Some(declared)) => {
+                validate_inputs(&declared, &provided, false)?;
+                let inputs = prompt_inputs(&github, &declared, &provided).await?;
+                validate_inputs(&declared, &inputs, true)?;
+                inputs
+            }
+            Ok(None) => {
+                println!("{}", format!("\"{}\" has no workflow_dispatch trigger at \"{}\"", workflow.path, self.command.ref_name).yellow());
+                provided
+            }
+            Err(e) => {
+                println!("{}", format!("Could not read the inputs of \"{}\": {}", workflow.path, e).yellow());
+                provided
+            }
+        };
 
-        let confirm = Confirm::with_theme(&ColorfulTheme::default())
-            .with_prompt(&format!("Run \"{}\"({}) action in \"{}\" tree?", workflow.name, workflow.html_url, self.command.ref_name))
-            .interact()?;
+        let prompt = format!("Run \"{}\"({}) action in \"{}\" tree?", workflow.name, workflow.html_url, self.command.ref_name);
+        let confirm = self.command.confirm(&prompt, &format!("run \"{}\"", workflow.name))?;
 
         if confirm {
-            let url = format!("https://api.github.com/repos/{}/{}/actions/workflows/{}/dispatches", self.command.owner.clone(), self.command.repo.clone(), workflow.id);
-            let _ = github.github_request(&url, "POST", Some(json!({ "ref": self.command.ref_name, "inputs": self.inputs })), None).await?;
+            let dispatched_at = Utc::now() - Duration::seconds(CLOCK_SKEW_SECONDS);
+            let url = github.repo_api_url(&format!("/actions/workflows/{}/dispatches", workflow.id));
+            github.github_request(&url, "POST", Some(json!({ "ref": self.command.ref_name, "inputs": inputs })), None).await
+                .map_err(|e| GarError::Dispatch {
+                    workflow: workflow.name.clone(),
+                    ref_name: self.command.ref_name.clone(),
+                    source: Box::new(e),
+                })?;
 
             println!("GitHub action successfully triggered.");
-            println!("Actions: https://github.com/{}/{}/actions", self.command.owner.clone(), self.command.repo.clone());
-            println!("Tree: https://github.com/{}/tree/{}", self.command.repo.clone(), self.command.ref_name);
+            println!("Actions: {}", github.repo_web_url("/actions"));
+            println!("Tree: {}", github.repo_web_url(&format!("/tree/{}", self.command.ref_name)));
 
             beep(1);
 
-            tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-            // Get the ID of the last run.
-            let runs = github.get_workflow_runs(workflow.id).await?;
-            let run_id = runs.first().map(|r| r.id).ok_or("No runs found")?;
-
-            println!("Action: https://github.com/{}/{}/actions/runs/{}", self.command.owner.clone(), self.command.repo.clone(), run_id);
-
-            let pb = Arc::new(Mutex::new(ProgressBar::new_spinner()));
-            pb.lock().await.set_style(ProgressStyle::default_spinner()
-                .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏")
-                .template("{spinner} Waiting for the workflow run to complete...").unwrap());
-
-            let pb_clone = Arc::clone(&pb);
-            tokio::spawn(async move {
-                update_progress_bar(pb_clone).await;
-            });
-            // Wait for the workflow run to complete.
-            loop {
-                match github.get_workflow_run(run_id).await? {
-                    Some(run) => {
-                        match run.status.as_str() {
-                            "completed" | "failure" => {
-                                let pb = pb.lock().await;
-                                pb.finish_with_message("GitHub action completed");
-                                println!("");
-                                println!("GitHub action completed with conclusion: {}", run.conclusion.clone().unwrap_or_else(|| "unknown".to_string()));
-                                beep(3);
-                                break;
-                            },
-                            _ => {
-
-                            }
-                        }
-                    }
-                    None => {
-
-                    }
-                }
-                tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+            let run = self.find_dispatched_run(&github, &workflow, dispatched_at, correlation_id.as_deref()).await?;
+            let run_id = run.id;
+
+            println!("Action: {}", github.repo_web_url(&format!("/actions/runs/{}", run_id)));
+
+            if self.no_wait {
+                println!("Run ID: {}", run_id);
+                return Ok(());
             }
-        } else {
-            println!("{}", "Cancel".red());
+
+            let run = wait_for_run(&github, run_id, self.command.timeout).await?;
+            conclusion_result(&run)?;
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    fn generate_correlation_id() -> String {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+        format!("gar-{:x}-{:x}", nanos, std::process::id())
+    }
+
+    // Finds the run created by our dispatch: a workflow_dispatch run on the dispatched ref by the
+    // token's user, created after the dispatch, and carrying the correlation id in its name if one was injected
+    async fn find_dispatched_run(
+        &self,
+        github: &GitHub,
+        workflow: &Workflow,
+        dispatched_at: DateTime<Utc>,
+        correlation_id: Option<&str>,
+    ) -> Result<WorkflowRun, GarError> {
+        let filter = RunFilter {
+            branch: Some(self.command.ref_name.trim_start_matches("refs/heads/").to_string()),
+            event: Some("workflow_dispatch".to_string()),
+            actor: github.get_authenticated_user().await.ok(),
+            created: Some(format!(">={}", dispatched_at.to_rfc3339_opts(SecondsFormat::Secs, true))),
+            ..RunFilter::default()
+        };
+
+        let started = Instant::now();
+        println!("Waiting for the dispatched run to appear...");
+        loop {
+            let runs = github.get_workflow_runs(workflow.id, &filter, None).await?;
+            // The query already filters, but older GHES versions ignore some of these parameters
+            let mut candidates: Vec<&WorkflowRun> = runs.iter()
+                .filter(|run| run.event == "workflow_dispatch")
+                .filter(|run| match (&filter.actor, &run.actor) {
+                    (Some(login), Some(actor)) => &actor.login == login,
+                    _ => true,
+                })
+                .filter(|run| DateTime::parse_from_rfc3339(&run.created_at).is_ok_and(|created| created >= dispatched_at))
+                .collect();
+            if let Some(id) = correlation_id {
+                candidates.retain(|run| run.display_title.contains(id) || run.name.contains(id));
+            }
+            // Of several candidates, the earliest one is the closest to our dispatch
+            candidates.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+            if let Some(run) = candidates.first() {
+                return Ok((*run).clone());
+            }
+
+            if started.elapsed() >= RUN_LOOKUP_TIMEOUT {
+                let mut message = format!("No run of \"{}\" appeared within {}s of the dispatch", workflow.name, RUN_LOOKUP_TIMEOUT.as_secs());
+                if let (Some(id), Some(name)) = (correlation_id, &self.correlation_input) {
+                    message = format!("{}. No run name contains \"{}\"; set `run-name: ${{{{ inputs.{} }}}}` in the workflow", message, id, name);
+                }
+                return Err(GarError::Other(message));
+            }
+            tokio::time::sleep(github.poll_interval(std::time::Duration::from_secs(3))).await;
+        }
+    }
+}
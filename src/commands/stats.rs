@@ -0,0 +1,306 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use serde::Serialize;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use crate::error::GarError;
+use crate::github::{Job, WorkflowRun};
+use crate::helpers::{elapsed_seconds, format_duration};
+use crate::output::{print_records, print_value, OutputFormat};
+use super::command::Command;
+use super::history::RunQuery;
+
+const DEFAULT_STATS_LIMIT: usize = 50;
+const DEFAULT_CONCURRENCY: usize = 4;
+// Only the slowest steps are worth a row
+const STEP_ROWS: usize = 20;
+// Conclusions that say nothing about whether the workflow works
+const IGNORED_CONCLUSIONS: [&str; 2] = ["cancelled", "skipped"];
+
+#[derive(Serialize)]
+struct Summary {
+    runs: usize,
+    completed: usize,
+    conclusions: BTreeMap<String, usize>,
+    success_rate: Option<f64>,
+    median_duration_seconds: Option<i64>,
+    p90_duration_seconds: Option<i64>,
+    median_queue_seconds: Option<i64>,
+    p90_queue_seconds: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct TimingRow {
+    name: String,
+    runs: usize,
+    success_rate: Option<f64>,
+    median_seconds: Option<i64>,
+    p90_seconds: Option<i64>,
+    max_seconds: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    median_queue_seconds: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct StatsReport {
+    summary: Summary,
+    jobs: Vec<TimingRow>,
+    steps: Vec<TimingRow>,
+    trend: Vec<TimingRow>,
+}
+
+// Durations and outcomes collected for one job, step or period
+#[derive(Default)]
+struct Samples {
+    durations: Vec<i64>,
+    queues: Vec<i64>,
+    succeeded: usize,
+    counted: usize,
+}
+
+impl Samples {
+    fn add_outcome(&mut self, conclusion: Option<&str>) {
+        match conclusion {
+            Some(conclusion) if !IGNORED_CONCLUSIONS.contains(&conclusion) => {
+                self.counted += 1;
+                if conclusion == "success" {
+                    self.succeeded += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn success_rate(&self) -> Option<f64> {
+        (self.counted > 0).then(|| self.succeeded as f64 * 100.0 / self.counted as f64)
+    }
+
+    fn row(mut self, name: String, with_queue: bool) -> TimingRow {
+        self.durations.sort_unstable();
+        self.queues.sort_unstable();
+        TimingRow {
+            name,
+            runs: self.durations.len().max(self.counted),
+            success_rate: self.success_rate(),
+            median_seconds: percentile(&self.durations, 0.5),
+            p90_seconds: percentile(&self.durations, 0.9),
+            max_seconds: self.durations.last().copied(),
+            median_queue_seconds: if with_queue { percentile(&self.queues, 0.5) } else { None },
+        }
+    }
+}
+
+pub(crate) struct StatsOptions {
+    pub(crate) query: RunQuery,
+    pub(crate) weekly: bool,
+    pub(crate) concurrency: Option<usize>,
+}
+
+pub struct StatsCommand {
+    command: Command,
+    options: StatsOptions,
+}
+
+impl StatsCommand {
+    pub fn new(command: Command, options: StatsOptions) -> Self {
+        StatsCommand { command, options }
+    }
+
+    pub async fn run(&self) -> Result<(), GarError> {
+        let github = Arc::new(self.command.github());
+        let runs = self.options.query.fetch(&self.command, &github, DEFAULT_STATS_LIMIT).await?;
+        if runs.is_empty() {
+            return Err(GarError::Selection("No runs match the filters".to_string()));
+        }
+        if self.command.output.is_table() {
+            println!("Analyzing {} runs...", runs.len());
+        }
+
+        // Jobs are fetched per run, a few runs at a time
+        let semaphore = Arc::new(Semaphore::new(self.options.concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1)));
+        let mut tasks = JoinSet::new();
+        for (position, run) in runs.iter().enumerate().filter(|(_, run)| run.status == "completed") {
+            let github = github.clone();
+            let semaphore = semaphore.clone();
+            let run_id = run.id;
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                (position, github.get_run_jobs(run_id).await)
+            });
+        }
+        let mut jobs: HashMap<usize, Vec<Job>> = HashMap::new();
+        while let Some(joined) = tasks.join_next().await {
+            let (position, result) = joined.map_err(|e| GarError::Other(e.to_string()))?;
+            jobs.insert(position, result?);
+        }
+
+        let report = self.build_report(&runs, &jobs);
+        self.print_report(report)
+    }
+
+    fn build_report(&self, runs: &[WorkflowRun], jobs: &HashMap<usize, Vec<Job>>) -> StatsReport {
+        let mut overall = Samples::default();
+        let mut conclusions = BTreeMap::new();
+        let mut job_samples: Vec<(String, Samples)> = Vec::new();
+        let mut step_samples: Vec<(String, Samples)> = Vec::new();
+        let mut periods: BTreeMap<String, Samples> = BTreeMap::new();
+
+        for (position, run) in runs.iter().enumerate() {
+            if run.status != "completed" {
+                continue;
+            }
+            let conclusion = run.conclusion.as_deref();
+            *conclusions.entry(conclusion.unwrap_or("unknown").to_string()).or_insert(0) += 1;
+            let run_jobs = jobs.get(&position).map(|jobs| jobs.as_slice()).unwrap_or_default();
+
+            let started_at = run.run_started_at.clone().or_else(|| Some(run.created_at.clone()));
+            let duration = elapsed_seconds(&started_at, &Some(run.updated_at.clone()));
+            // Time from the start of the attempt until its first job got a runner
+            let first_job = run_jobs.iter().filter_map(|job| job.started_at.clone()).min();
+            let queue = first_job.and_then(|first_job| elapsed_seconds(&started_at, &Some(first_job))).map(|seconds| seconds.max(0));
+
+            let period = self.period(&run.created_at);
+            for samples in [&mut overall, periods.entry(period).or_default()] {
+                samples.add_outcome(conclusion);
+                samples.durations.extend(duration);
+                samples.queues.extend(queue);
+            }
+
+            for job in run_jobs {
+                let samples = entry(&mut job_samples, &job.name);
+                samples.add_outcome(job.conclusion.as_deref());
+                samples.durations.extend(elapsed_seconds(&job.started_at, &job.completed_at));
+
+                for step in &job.steps {
+                    let samples = entry(&mut step_samples, &format!("{} / {}", job.name, step.name));
+                    samples.add_outcome(step.conclusion.as_deref());
+                    samples.durations.extend(elapsed_seconds(&step.started_at, &step.completed_at));
+                }
+            }
+        }
+
+        let completed = overall.counted + IGNORED_CONCLUSIONS.iter().filter_map(|c| conclusions.get(*c)).sum::<usize>();
+        overall.queues.sort_unstable();
+        let p90_queue_seconds = percentile(&overall.queues, 0.9);
+        let overall = overall.row(String::new(), true);
+        let summary = Summary {
+            runs: runs.len(),
+            completed,
+            conclusions,
+            success_rate: overall.success_rate,
+            median_duration_seconds: overall.median_seconds,
+            p90_duration_seconds: overall.p90_seconds,
+            median_queue_seconds: overall.median_queue_seconds,
+            p90_queue_seconds,
+        };
+
+        let mut steps: Vec<TimingRow> = step_samples.into_iter().map(|(name, samples)| samples.row(name, false)).collect();
+        steps.sort_by_key(|step| std::cmp::Reverse(step.median_seconds));
+        steps.truncate(STEP_ROWS);
+
+        StatsReport {
+            summary,
+            jobs: job_samples.into_iter().map(|(name, samples)| samples.row(name, false)).collect(),
+            steps,
+            trend: periods.into_iter().map(|(period, samples)| samples.row(period, true)).collect(),
+        }
+    }
+
+    // "2024-05-13" for days, ISO weeks such as "2024-W20" otherwise
+    fn period(&self, created_at: &str) -> String {
+        match DateTime::parse_from_rfc3339(created_at) {
+            Ok(created) if self.options.weekly => created.with_timezone(&Utc).format("%G-W%V").to_string(),
+            Ok(created) => created.with_timezone(&Utc).format("%Y-%m-%d").to_string(),
+            Err(_) => "unknown".to_string(),
+        }
+    }
+
+    fn print_report(&self, report: StatsReport) -> Result<(), GarError> {
+        let output = self.command.output;
+        if matches!(output, OutputFormat::Json | OutputFormat::Yaml) {
+            return print_value(output, &report);
+        }
+
+        let summary = &report.summary;
+        let conclusions = summary.conclusions.iter().map(|(conclusion, count)| format!("{} {}", count, conclusion)).collect::<Vec<_>>().join(", ");
+        let summary_rows = vec![
+            vec!["Runs".to_string(), format!("{} ({} completed)", summary.runs, summary.completed)],
+            vec!["Conclusions".to_string(), conclusions],
+            vec!["Success rate".to_string(), format_rate(summary.success_rate)],
+            vec!["Median duration".to_string(), format_seconds(summary.median_duration_seconds)],
+            vec!["P90 duration".to_string(), format_seconds(summary.p90_duration_seconds)],
+            vec!["Median queue time".to_string(), format_seconds(summary.median_queue_seconds)],
+            vec!["P90 queue time".to_string(), format_seconds(summary.p90_queue_seconds)],
+        ];
+        self.section("Summary", output)?;
+        print_records(output, &(), &["Metric", "Value"], &summary_rows)?;
+
+        let timing_headers = ["Name", "Runs", "Success rate", "Median", "P90", "Max"];
+        let timing_rows = |rows: &[TimingRow]| -> Vec<Vec<String>> {
+            rows.iter().map(|row| vec![
+                row.name.clone(),
+                row.runs.to_string(),
+                format_rate(row.success_rate),
+                format_seconds(row.median_seconds),
+                format_seconds(row.p90_seconds),
+                format_seconds(row.max_seconds),
+            ]).collect()
+        };
+
+        self.section("Jobs", output)?;
+        print_records(output, &report.jobs, &timing_headers, &timing_rows(&report.jobs))?;
+        self.section(&format!("Slowest steps (top {})", STEP_ROWS), output)?;
+        print_records(output, &report.steps, &timing_headers, &timing_rows(&report.steps))?;
+
+        let trend_rows: Vec<Vec<String>> = report.trend.iter().map(|row| vec![
+            row.name.clone(),
+            row.runs.to_string(),
+            format_rate(row.success_rate),
+            format_seconds(row.median_seconds),
+            format_seconds(row.p90_seconds),
+            format_seconds(row.median_queue_seconds),
+        ]).collect();
+        self.section("Trend", output)?;
+        print_records(output, &report.trend, &[if self.options.weekly { "Week" } else { "Day" }, "Runs", "Success rate", "Median", "P90", "Median queue"], &trend_rows)
+    }
+
+    fn section(&self, title: &str, output: OutputFormat) -> Result<(), GarError> {
+        match output {
+            OutputFormat::Markdown => println!("\n### {}\n", title),
+            OutputFormat::Csv => println!(),
+            _ => println!("\n{}", title.bold()),
+        }
+        Ok(())
+    }
+}
+
+fn entry<'a>(samples: &'a mut Vec<(String, Samples)>, name: &str) -> &'a mut Samples {
+    // Keeps the order in which jobs and steps first appear
+    let index = match samples.iter().position(|(existing, _)| existing == name) {
+        Some(index) => index,
+        None => {
+            samples.push((name.to_string(), Samples::default()));
+            samples.len() - 1
+        }
+    };
+    &mut samples[index].1
+}
+
+// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[i64], fraction: f64) -> Option<i64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (fraction * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+fn format_rate(rate: Option<f64>) -> String {
+    rate.map(|rate| format!("{:.1}%", rate)).unwrap_or_else(|| "N/A".to_string())
+}
+
+fn format_seconds(seconds: Option<i64>) -> String {
+    seconds.map(format_duration).unwrap_or_else(|| "N/A".to_string())
+}
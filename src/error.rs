@@ -0,0 +1,201 @@
+use std::fmt;
+use std::sync::LazyLock;
+use regex::Regex;
+use serde::Deserialize;
+
+// Messages of failed dispatches that have a more helpful explanation
+static UNEXPECTED_INPUTS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"Unexpected inputs provided: \[(.*)\]").unwrap());
+static MISSING_INPUT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"Required input '([^']+)' not provided").unwrap());
+static MISSING_REF: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"No ref found for: (.*)").unwrap());
+
+// The error payload GitHub returns alongside 4xx/5xx responses
+#[derive(Deserialize, Debug, Default)]
+pub struct ApiError {
+    pub(crate) message: String,
+    pub(crate) documentation_url: Option<String>,
+    #[serde(default)]
+    pub(crate) errors: Vec<serde_json::Value>,
+}
+
+impl ApiError {
+    // Entries in `errors` are either plain strings or {resource, field, code, message} objects
+    fn details(&self) -> Vec<String> {
+        self.errors.iter().map(|error| match error {
+            serde_json::Value::String(message) => message.clone(),
+            error => match error["message"].as_str() {
+                Some(message) => message.to_string(),
+                None => [&error["resource"], &error["field"], &error["code"]].iter()
+                    .filter_map(|value| value.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            },
+        }).collect()
+    }
+
+    fn hint(&self, status: u16) -> Option<String> {
+        let message = self.message.as_str();
+        if let Some(caps) = UNEXPECTED_INPUTS.captures(message) {
+            let inputs = caps[1].replace('"', "");
+            return Some(format!("The workflow does not declare the input(s) {} under on.workflow_dispatch.inputs at this ref", inputs));
+        }
+        if let Some(caps) = MISSING_INPUT.captures(message) {
+            return Some(format!("Pass the required input with --inputs {}=<value>", &caps[1]));
+        }
+        if message.contains("does not have 'workflow_dispatch' trigger") {
+            return Some("Add a `workflow_dispatch:` trigger to the workflow's `on:` section and push it to the ref being dispatched".to_string());
+        }
+        if let Some(caps) = MISSING_REF.captures(message) {
+            return Some(format!("The ref \"{}\" does not exist on the remote; push it or pass another one with --ref", caps[1].trim()));
+        }
+        match status {
+            401 => Some("The token is invalid or expired".to_string()),
+            403 if message.contains("Resource not accessible") => Some("The token lacks the `actions: write` permission for this repository".to_string()),
+            404 => Some("The resource does not exist or the token cannot access it".to_string()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum GarError {
+    Auth(String),
+    NotARepo(String),
+    RemoteParse(String),
+    Http { status: u16, error: ApiError },
+    Dispatch { workflow: String, ref_name: String, source: Box<GarError> },
+    InvalidInputs(Vec<String>),
+    Selection(String),
+    RunConclusion { conclusion: String, url: String },
+    Timeout(String),
+    Interrupted(String),
+    Decode(String),
+    Archive(String),
+    Network(String),
+    RateLimited(String),
+    Io(String),
+    Prompt(String),
+    Other(String),
+}
+
+impl GarError {
+    // 2 is left to clap for usage errors
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            GarError::Other(_) => 1,
+            GarError::Auth(_) => 3,
+            GarError::NotARepo(_) => 4,
+            GarError::RemoteParse(_) => 5,
+            GarError::Http { status: 401, .. } => 3,
+            GarError::Http { .. } => 6,
+            GarError::Dispatch { source, .. } => source.exit_code(),
+            GarError::Decode(_) => 7,
+            GarError::Archive(_) => 8,
+            GarError::Network(_) => 9,
+            GarError::RateLimited(_) => 10,
+            GarError::Io(_) => 11,
+            GarError::Prompt(_) => 12,
+            GarError::InvalidInputs(_) => 13,
+            GarError::Selection(_) => 14,
+            GarError::RunConclusion { conclusion, .. } => match conclusion.as_str() {
+                "failure" => 20,
+                "timed_out" => 21,
+                "startup_failure" => 22,
+                "cancelled" => 23,
+                _ => 24,
+            },
+            // Same codes as coreutils `timeout` and a shell's SIGINT
+            GarError::Timeout(_) => 124,
+            GarError::Interrupted(_) => 130,
+        }
+    }
+}
+
+impl fmt::Display for GarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GarError::Auth(message) => write!(f, "Authentication failed: {}", message),
+            GarError::NotARepo(message) => write!(f, "Not a git repository: {}", message),
+            GarError::RemoteParse(message) => write!(f, "Could not determine the repository from the origin remote: {}", message),
+            GarError::Http { status, error } => {
+                write!(f, "GitHub responded with {}: {}", status, error.message)?;
+                for detail in error.details() {
+                    write!(f, "\n  - {}", detail)?;
+                }
+                if let Some(hint) = error.hint(*status) {
+                    write!(f, "\n  Hint: {}", hint)?;
+                }
+                if let Some(url) = &error.documentation_url {
+                    write!(f, "\n  Docs: {}", url)?;
+                }
+                Ok(())
+            }
+            GarError::Dispatch { workflow, ref_name, source } => write!(f, "Dispatch of \"{}\" on \"{}\" was rejected. {}", workflow, ref_name, source),
+            GarError::InvalidInputs(problems) => {
+                write!(f, "Invalid workflow inputs:")?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+            GarError::Selection(message) => write!(f, "{}", message),
+            GarError::RunConclusion { conclusion, url } => write!(f, "The workflow run concluded with \"{}\": {}", conclusion, url),
+            GarError::Timeout(message) => write!(f, "{}", message),
+            GarError::Interrupted(message) => write!(f, "{}", message),
+            GarError::Decode(message) => write!(f, "Could not decode the GitHub response: {}", message),
+            GarError::Archive(message) => write!(f, "Could not read the archive: {}", message),
+            GarError::Network(message) => write!(f, "Network error: {}", message),
+            GarError::RateLimited(message) => write!(f, "{}", message),
+            GarError::Io(message) => write!(f, "I/O error: {}", message),
+            GarError::Prompt(message) => write!(f, "Prompt failed: {}", message),
+            GarError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for GarError {}
+
+impl From<reqwest::Error> for GarError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            GarError::Decode(e.to_string())
+        } else {
+            GarError::Network(e.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for GarError {
+    fn from(e: serde_json::Error) -> Self {
+        GarError::Decode(e.to_string())
+    }
+}
+
+impl From<zip::result::ZipError> for GarError {
+    fn from(e: zip::result::ZipError) -> Self {
+        GarError::Archive(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for GarError {
+    fn from(e: std::io::Error) -> Self {
+        GarError::Io(e.to_string())
+    }
+}
+
+impl From<dialoguer::Error> for GarError {
+    fn from(e: dialoguer::Error) -> Self {
+        GarError::Prompt(e.to_string())
+    }
+}
+
+impl From<String> for GarError {
+    fn from(message: String) -> Self {
+        GarError::Other(message)
+    }
+}
+
+impl From<&str> for GarError {
+    fn from(message: &str) -> Self {
+        GarError::Other(message.to_string())
+    }
+}
@@ -1,17 +1,38 @@
+use std::fs;
+use std::io::{Seek, SeekFrom, Write};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
 use colored::Colorize;
 use dialoguer::{Select};
 use dialoguer::theme::ColorfulTheme;
-use reqwest::{Client, Response};
-use serde::Deserialize;
+use regex::Regex;
+use reqwest::{Client, Method, Response, StatusCode};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use crate::error::{ApiError, GarError};
+use crate::rate_limit::{backoff, retry_for, RateLimit, Retry, MAX_RETRIES};
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Serialize, Clone)]
 pub struct Workflow {
     pub(crate) id: u64,
     pub(crate) name: String,
+    pub(crate) path: String,
     pub(crate) html_url: String,
 }
 
 #[derive(Deserialize, Clone)]
+pub(crate) struct Environment {
+    pub(crate) name: String,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub(crate) struct Actor {
+    pub(crate) login: String,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
 pub(crate) struct WorkflowRun {
     pub(crate) id: u64,
     pub(crate) name: String,
@@ -20,28 +41,206 @@ pub(crate) struct WorkflowRun {
     pub(crate) status: String,
     pub(crate) conclusion: Option<String>,
     pub(crate) head_branch: String,
+    #[serde(default)]
+    pub(crate) event: String,
+    pub(crate) actor: Option<Actor>,
+    #[serde(default)]
+    pub(crate) run_attempt: u64,
     pub(crate) logs_url: String,
     pub(crate) created_at: String,
     pub(crate) updated_at: String,
+    #[serde(default)]
+    pub(crate) run_started_at: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub(crate) struct Artifact {
+    pub(crate) id: u64,
+    pub(crate) name: String,
+    pub(crate) size_in_bytes: u64,
+    #[serde(default)]
+    pub(crate) expired: bool,
+    pub(crate) created_at: Option<String>,
+    pub(crate) expires_at: Option<String>,
+    pub(crate) archive_download_url: String,
+    pub(crate) workflow_run: Option<ArtifactRun>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub(crate) struct ArtifactRun {
+    pub(crate) id: u64,
+    pub(crate) head_branch: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub(crate) struct Cache {
+    pub(crate) id: u64,
+    pub(crate) key: String,
+    #[serde(rename = "ref")]
+    pub(crate) ref_name: String,
+    pub(crate) size_in_bytes: u64,
+    pub(crate) created_at: Option<String>,
+    pub(crate) last_accessed_at: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub(crate) struct Step {
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) number: u64,
+    pub(crate) status: String,
+    pub(crate) conclusion: Option<String>,
+    pub(crate) started_at: Option<String>,
+    pub(crate) completed_at: Option<String>,
 }
 
+#[derive(Deserialize, Serialize, Clone)]
+pub(crate) struct Job {
+    pub(crate) id: u64,
+    pub(crate) name: String,
+    pub(crate) status: String,
+    pub(crate) conclusion: Option<String>,
+    pub(crate) started_at: Option<String>,
+    pub(crate) completed_at: Option<String>,
+    #[serde(default)]
+    pub(crate) steps: Vec<Step>,
+}
+
+// Query parameters accepted by the workflow runs endpoints
+#[derive(Default, Clone)]
+pub(crate) struct RunFilter {
+    pub(crate) branch: Option<String>,
+    pub(crate) event: Option<String>,
+    pub(crate) actor: Option<String>,
+    pub(crate) status: Option<String>,
+    pub(crate) created: Option<String>,
+}
+
+impl RunFilter {
+    fn query(&self) -> String {
+        let params = [
+            ("branch", &self.branch),
+            ("event", &self.event),
+            ("actor", &self.actor),
+            ("status", &self.status),
+            ("created", &self.created),
+        ];
+        params.iter()
+            .filter_map(|(key, value)| value.as_ref().map(|value| format!("{}={}", key, encode_query_value(value))))
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+}
+
+fn encode_query_value(value: &str) -> String {
+    value.bytes().map(|byte| match byte {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+        _ => format!("%{:02X}", byte),
+    }).collect()
+}
+
+#[derive(Deserialize)]
+struct User {
+    login: String,
+}
+
+pub const DEFAULT_API_URL: &str = "https://api.github.com";
+pub const DEFAULT_PER_PAGE: u32 = 100;
+const SELECT_RUN_LIMIT: usize = 100;
+// The `Link` header of paginated responses, e.g. `<https://api.github.com/...&page=2>; rel="next"`
+static NEXT_LINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"<([^>]+)>;\s*rel="next""#).unwrap());
+
 pub struct GitHub {
     token: String,
     owner: String,
     repo: String,
+    api_url: String,
+    per_page: u32,
+    max_items: Option<usize>,
+    interactive: bool,
+    client: Client,
+    rate_limit: Mutex<Option<RateLimit>>,
 }
 
 impl GitHub {
-    pub fn new(token: String, owner: String, repo: String) -> GitHub {
-        GitHub { token, owner, repo }
+    pub fn new(token: String, owner: String, repo: String, api_url: String) -> GitHub {
+        let api_url = api_url.trim_end_matches('/').to_string();
+        let client = Client::builder()
+            .redirect(reqwest::redirect::Policy::limited(10))
+            .build()
+            .unwrap_or_default();
+
+        GitHub {
+            token,
+            owner,
+            repo,
+            api_url,
+            per_page: DEFAULT_PER_PAGE,
+            max_items: None,
+            interactive: true,
+            client,
+            rate_limit: Mutex::new(None),
+        }
     }
 
-    pub(crate) async fn select_workflow(&self) -> Result<Workflow, Box<dyn std::error::Error>> {
-        let url = format!("https://api.github.com/repos/{}/{}/actions/workflows", self.owner, self.repo);
-        let workflows_data = self.github_request(&url, "GET", None, None)
-            .await?;
-        let workflows: Vec<Workflow> = serde_json::from_value(workflows_data["workflows"].clone())
-            .map_err(|e| format!("Bad request, check token or permissions. Original error: {}", e))?;
+    pub fn with_pagination(mut self, per_page: u32, max_items: Option<usize>) -> GitHub {
+        self.per_page = per_page.clamp(1, 100);
+        self.max_items = max_items;
+        self
+    }
+
+    pub fn with_interactive(mut self, interactive: bool) -> GitHub {
+        self.interactive = interactive;
+        self
+    }
+
+    pub(crate) fn is_interactive(&self) -> bool {
+        self.interactive
+    }
+
+    // github.com and GHE.com tenants serve the API from an `api.` subdomain, GHES serves it under /api/v3
+    pub(crate) fn api_url_for_host(host: &str) -> String {
+        if host == "github.com" || host == "www.github.com" {
+            DEFAULT_API_URL.to_string()
+        } else if host.ends_with(".ghe.com") {
+            format!("https://api.{}", host)
+        } else {
+            format!("https://{}/api/v3", host)
+        }
+    }
+
+    pub(crate) fn web_url(&self) -> String {
+        if let Some(base) = self.api_url.strip_suffix("/api/v3") {
+            base.to_string()
+        } else if let Some(host) = self.api_url.strip_prefix("https://api.") {
+            format!("https://{}", host)
+        } else {
+            self.api_url.clone()
+        }
+    }
+
+    pub(crate) fn repo_api_url(&self, path: &str) -> String {
+        format!("{}/repos/{}/{}{}", self.api_url, self.owner, self.repo, path)
+    }
+
+    pub(crate) fn repo_web_url(&self, path: &str) -> String {
+        format!("{}/{}/{}{}", self.web_url(), self.owner, self.repo, path)
+    }
+
+    // `query` (--workflow) skips the prompt; without it a prompt is only possible in interactive mode
+    pub(crate) async fn select_workflow(&self, query: Option<&str>) -> Result<Workflow, GarError> {
+        let url = self.repo_api_url("/actions/workflows");
+        let workflows: Vec<Workflow> = self.github_request_paginated(&url, "workflows", None).await?;
+        if workflows.is_empty() {
+            return Err(GarError::Selection("No workflows found in the repository".to_string()));
+        }
+
+        if let Some(query) = query {
+            return Self::find_workflow(&workflows, query);
+        }
+        if !self.interactive {
+            return Err(GarError::Selection("No workflow selected; pass --workflow <name|file|id> in non-interactive mode".to_string()));
+        }
 
         let workflow_names: Vec<String> = workflows.iter().map(|wf| {
             let mut name = wf.name.clone();
@@ -63,10 +262,78 @@ impl GitHub {
         Ok(workflows[selected].clone())
     }
 
-    pub(crate) async fn select_run(&self, workflow_id: u64) -> Result<WorkflowRun, Box<dyn std::error::Error>> {
-        let url = format!("https://api.github.com/repos/{}/{}/actions/workflows/{}/runs", self.owner, self.repo, workflow_id);
-        let runs_data = self.github_request(&url, "GET", None, None).await?;
-        let runs: Vec<WorkflowRun> = serde_json::from_value(runs_data["workflow_runs"].clone())?;
+    // Matches by id, file path or name first, then by a unique substring, then fuzzily
+    fn find_workflow(workflows: &[Workflow], query: &str) -> Result<Workflow, GarError> {
+        let query_lower = query.trim().to_lowercase();
+        let file_stem = |workflow: &Workflow| -> String {
+            let file = workflow.path.rsplit('/').next().unwrap_or(&workflow.path);
+            file.trim_end_matches(".yml").trim_end_matches(".yaml").to_lowercase()
+        };
+
+        let exact: Vec<&Workflow> = workflows.iter().filter(|workflow| {
+            workflow.id.to_string() == query_lower
+                || workflow.path.to_lowercase() == query_lower
+                || workflow.path.to_lowercase().ends_with(&format!("/{}", query_lower))
+                || file_stem(workflow) == query_lower
+                || workflow.name.to_lowercase() == query_lower
+        }).collect();
+        let partial: Vec<&Workflow> = workflows.iter()
+            .filter(|workflow| workflow.name.to_lowercase().contains(&query_lower) || file_stem(workflow).contains(&query_lower))
+            .collect();
+
+        let mut scored: Vec<(&Workflow, f64)> = workflows.iter().map(|workflow| {
+            let score = strsim::jaro_winkler(&query_lower, &workflow.name.to_lowercase())
+                .max(strsim::jaro_winkler(&query_lower, &file_stem(workflow)));
+            (workflow, score)
+        }).filter(|(_, score)| *score >= 0.85).collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        // A fuzzy match only counts when it clearly beats the runner-up
+        let fuzzy: Vec<&Workflow> = match scored.as_slice() {
+            [] => vec![],
+            [(best, _)] => vec![*best],
+            [(best, best_score), (_, second_score), ..] if best_score - second_score >= 0.05 => vec![*best],
+            candidates => candidates.iter().take_while(|(_, score)| scored[0].1 - score < 0.05).map(|(workflow, _)| *workflow).collect(),
+        };
+
+        for matches in [exact, partial, fuzzy] {
+            match matches.as_slice() {
+                [] => continue,
+                [workflow] => return Ok((*workflow).clone()),
+                candidates => {
+                    let names: Vec<String> = candidates.iter().map(|workflow| format!("{} ({})", workflow.name, workflow.path)).collect();
+                    return Err(GarError::Selection(format!("\"{}\" matches several workflows: {}", query, names.join(", "))));
+                }
+            }
+        }
+
+        let names: Vec<&str> = workflows.iter().map(|workflow| workflow.name.as_str()).collect();
+        Err(GarError::Selection(format!("No workflow matches \"{}\". Available workflows: {}", query, names.join(", "))))
+    }
+
+    // `--run` wins; otherwise a run of the selected workflow is picked among those in `statuses`
+    pub(crate) async fn resolve_run(&self, run_id: Option<u64>, workflow: Option<&str>, statuses: &[&str]) -> Result<WorkflowRun, GarError> {
+        match run_id {
+            Some(run_id) => self.get_workflow_run(run_id).await,
+            None => {
+                let workflow = self.select_workflow(workflow).await?;
+                self.select_run(workflow.id, statuses).await
+            }
+        }
+    }
+
+    // An empty `statuses` list offers runs in any status
+    pub(crate) async fn select_run(&self, workflow_id: u64, statuses: &[&str]) -> Result<WorkflowRun, GarError> {
+        let mut runs = self.get_workflow_runs(workflow_id, &RunFilter::default(), Some(SELECT_RUN_LIMIT)).await?;
+        if !statuses.is_empty() {
+            runs.retain(|run| statuses.contains(&run.status.as_str()));
+        }
+        if runs.is_empty() {
+            return Err(GarError::Selection("No matching runs found for the workflow".to_string()));
+        }
+        // Non-interactive callers get the most recent run
+        if !self.interactive {
+            return Ok(runs[0].clone());
+        }
 
         let run_ids: Vec<String> = runs.iter().map(|run| {
             let id = run.id.to_string();
@@ -97,7 +364,7 @@ impl GitHub {
         method: &str,
         data: Option<serde_json::Value>,
         accept: Option<&str>
-    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    ) -> Result<serde_json::Value, GarError> {
         let response = self.github_request_fn(url, method, data, accept).await?;
         let response_text = &response.text().await?;
 
@@ -109,13 +376,56 @@ impl GitHub {
         Ok(data)
     }
 
+    // Follows `Link: rel="next"` headers and collects `key` from every page (or the page itself when `key` is empty)
+    pub async fn github_request_paginated<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        key: &str,
+        limit: Option<usize>,
+    ) -> Result<Vec<T>, GarError> {
+        let limit = match (limit, self.max_items) {
+            (Some(limit), Some(max_items)) => Some(limit.min(max_items)),
+            (limit, max_items) => limit.or(max_items),
+        };
+        let separator = if url.contains('?') { '&' } else { '?' };
+        let mut next_url = Some(format!("{}{}per_page={}", url, separator, self.per_page));
+        let mut items: Vec<T> = Vec::new();
+
+        while let Some(page_url) = next_url {
+            let response = self.github_request_fn(&page_url, "GET", None, None).await?;
+            next_url = Self::next_page_url(response.headers());
+
+            let data: serde_json::Value = response.json().await?;
+            let page = if key.is_empty() { data } else { data[key].clone() };
+            let page: Vec<T> = serde_json::from_value(page)?;
+            if page.is_empty() {
+                break;
+            }
+            items.extend(page);
+
+            if let Some(limit) = limit {
+                if items.len() >= limit {
+                    items.truncate(limit);
+                    break;
+                }
+            }
+        }
+
+        Ok(items)
+    }
+
+    fn next_page_url(headers: &HeaderMap) -> Option<String> {
+        let link = headers.get("link")?.to_str().ok()?;
+        NEXT_LINK.captures(link).map(|caps| caps[1].to_string())
+    }
+
     pub async fn github_request_bytes(
         &self,
         url: &str,
         method: &str,
         data: Option<serde_json::Value>,
         accept: Option<&str>
-    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    ) -> Result<Vec<u8>, GarError> {
         let response = self.github_request_fn(url, method, data, accept).await?;
 
         let response_bytes = response.bytes().await?;
@@ -123,57 +433,287 @@ impl GitHub {
         Ok(response_bytes.to_vec())
     }
 
+    // Streams the response body into `file` chunk by chunk and rewinds it, for archives too large to hold in memory
+    pub(crate) async fn github_request_to_file(&self, url: &str, accept: Option<&str>, file: &mut fs::File) -> Result<u64, GarError> {
+        let mut response = self.github_request_fn(url, "GET", None, accept).await?;
+
+        let mut size = 0;
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk)?;
+            size += chunk.len() as u64;
+        }
+        file.flush()?;
+        file.seek(SeekFrom::Start(0))?;
+
+        Ok(size)
+    }
+
     pub async fn github_request_fn(
         &self,
         url: &str,
         method: &str,
         data: Option<serde_json::Value>,
         accept: Option<&str>
-    ) -> Result<Response, Box<dyn std::error::Error>> {
-        // let proxy = reqwest::Proxy::all("http://127.0.0.1:4034")?;
-        let client = Client::builder()
-            // .proxy(proxy)
-            .redirect(reqwest::redirect::Policy::limited(10))
-            .danger_accept_invalid_certs(true)
-            .build()?;
-
+    ) -> Result<Response, GarError> {
         let accept_header = accept.unwrap_or("application/vnd.github.v3+json");
+        let method = Method::from_bytes(method.as_bytes()).map_err(|e| GarError::Other(e.to_string()))?;
+        // A POST that timed out or failed with a 5xx may still have been carried out (a dispatch would create a
+        // second run), so only idempotent requests are resent then; rate-limit responses are safe for every method
+        let idempotent = method.is_idempotent();
+        let mut attempt = 0;
 
-        let response = match method {
-            "POST" => client.post(url)
-                .header("Accept", accept_header)
-                .header("Authorization", format!("token {}", self.token))
-                .header("User-Agent", "GAR")
-                .json(&data.unwrap())
-                .send()
-                .await?,
-            _ => client.get(url)
+        loop {
+            let mut request = self.client.request(method.clone(), url)
                 .header("Accept", accept_header)
                 .header("Authorization", format!("token {}", self.token))
-                .header("User-Agent", "GAR")
-                .send()
-                .await?,
-        };
+                .header("User-Agent", "GAR");
+            if let Some(data) = &data {
+                request = request.json(data);
+            }
 
-        Ok(response)
+            let response = match request.send().await {
+                Ok(response) => response,
+                Err(e) if (e.is_connect() || (e.is_timeout() && idempotent)) && attempt < MAX_RETRIES => {
+                    let wait = backoff(attempt);
+                    eprintln!("{}", format!("Request failed ({}), retrying in {}s...", e, wait.as_secs()).yellow());
+                    tokio::time::sleep(wait).await;
+                    attempt += 1;
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            if let Some(rate_limit) = RateLimit::from_headers(response.headers()) {
+                *self.rate_limit.lock().unwrap() = Some(rate_limit);
+            }
+
+            // Only the body of a 403 tells a secondary rate limit apart from missing permissions, so it is read up front
+            let status = response.status();
+            let headers = response.headers().clone();
+            let (response, body) = match status {
+                StatusCode::FORBIDDEN => (None, Some(response.text().await.unwrap_or_default())),
+                _ => (Some(response), None),
+            };
+
+            match retry_for(status, &headers, body.as_deref(), attempt, idempotent) {
+                Retry::Exhausted(message) => return Err(GarError::RateLimited(message)),
+                Retry::After(wait, reason) if attempt < MAX_RETRIES => {
+                    eprintln!("{}", format!("{}, retrying in {}s...", reason, wait.as_secs()).yellow());
+                    tokio::time::sleep(wait).await;
+                    attempt += 1;
+                }
+                _ => return match response {
+                    Some(response) => Self::check_status(response).await,
+                    None => Err(Self::http_error(status, &body.unwrap_or_default())),
+                },
+            }
+        }
     }
 
+    async fn check_status(response: Response) -> Result<Response, GarError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
 
-    pub(crate) async fn get_workflow_runs(&self, workflow_id: u64) -> Result<Vec<WorkflowRun>, Box<dyn std::error::Error>> {
-        let url = format!("https://api.github.com/repos/{}/{}/actions/workflows/{}/runs", self.owner, self.repo, workflow_id);
-        let data = self.github_request(&url, "GET", None, None).await?;
-        let runs: Vec<WorkflowRun> = serde_json::from_value(data["workflow_runs"].clone())?;
-        Ok(runs)
+        let body = response.text().await.unwrap_or_default();
+        Err(Self::http_error(status, &body))
+    }
+
+    fn http_error(status: StatusCode, body: &str) -> GarError {
+        let error = serde_json::from_str::<ApiError>(body).unwrap_or_else(|_| ApiError {
+            message: match body.trim() {
+                "" => status.canonical_reason().unwrap_or("Unknown error").to_string(),
+                text => text.to_string(),
+            },
+            ..ApiError::default()
+        });
+
+        GarError::Http { status: status.as_u16(), error }
     }
 
-    pub(crate) async fn get_workflow_run(&self, run_id: u64) -> Result<Option<WorkflowRun>, Box<dyn std::error::Error>> {
-        let url = format!("https://api.github.com/repos/{}/{}/actions/runs/{}", self.owner, self.repo, run_id);
+    // Polling loops call this instead of sleeping a fixed interval so they slow down when the budget runs low
+    pub(crate) fn poll_interval(&self, base: Duration) -> Duration {
+        match *self.rate_limit.lock().unwrap() {
+            Some(rate_limit) => rate_limit.poll_interval(base),
+            None => base,
+        }
+    }
+
+    pub(crate) async fn get_workflow_runs(&self, workflow_id: u64, filter: &RunFilter, limit: Option<usize>) -> Result<Vec<WorkflowRun>, GarError> {
+        let mut url = self.repo_api_url(&format!("/actions/workflows/{}/runs", workflow_id));
+        let query = filter.query();
+        if !query.is_empty() {
+            url = format!("{}?{}", url, query);
+        }
+        self.github_request_paginated(&url, "workflow_runs", limit).await
+    }
+
+    // Runs of every workflow of the repository
+    pub(crate) async fn get_repo_runs(&self, filter: &RunFilter, limit: Option<usize>) -> Result<Vec<WorkflowRun>, GarError> {
+        let mut url = self.repo_api_url("/actions/runs");
+        let query = filter.query();
+        if !query.is_empty() {
+            url = format!("{}?{}", url, query);
+        }
+        self.github_request_paginated(&url, "workflow_runs", limit).await
+    }
+
+    pub(crate) async fn cancel_run(&self, run_id: u64, force: bool) -> Result<(), GarError> {
+        let endpoint = if force { "force-cancel" } else { "cancel" };
+        let url = self.repo_api_url(&format!("/actions/runs/{}/{}", run_id, endpoint));
+        self.github_request(&url, "POST", None, None).await?;
+        Ok(())
+    }
+
+    pub(crate) async fn rerun_run(&self, run_id: u64, failed_only: bool, debug: bool) -> Result<(), GarError> {
+        let endpoint = if failed_only { "rerun-failed-jobs" } else { "rerun" };
+        let url = self.repo_api_url(&format!("/actions/runs/{}/{}", run_id, endpoint));
+        self.github_request(&url, "POST", Some(json!({ "enable_debug_logging": debug })), None).await?;
+        Ok(())
+    }
+
+    pub(crate) async fn rerun_job(&self, job_id: u64, debug: bool) -> Result<(), GarError> {
+        let url = self.repo_api_url(&format!("/actions/jobs/{}/rerun", job_id));
+        self.github_request(&url, "POST", Some(json!({ "enable_debug_logging": debug })), None).await?;
+        Ok(())
+    }
+
+    pub(crate) async fn get_job_logs(&self, job_id: u64) -> Result<String, GarError> {
+        let url = self.repo_api_url(&format!("/actions/jobs/{}/logs", job_id));
+        let data = self.github_request_bytes(&url, "GET", None, None).await?;
+        Ok(String::from_utf8_lossy(&data).into_owned())
+    }
+
+    // Jobs of the latest attempt of the run
+    pub(crate) async fn get_run_jobs(&self, run_id: u64) -> Result<Vec<Job>, GarError> {
+        let url = self.repo_api_url(&format!("/actions/runs/{}/jobs?filter=latest", run_id));
+        self.github_request_paginated(&url, "jobs", None).await
+    }
+
+    pub(crate) async fn get_authenticated_user(&self) -> Result<String, GarError> {
+        let url = format!("{}/user", self.api_url);
         let data = self.github_request(&url, "GET", None, None).await?;
-        if data.is_null() {
-            Ok(None)
-        } else {
-            let run: WorkflowRun = serde_json::from_value(data)?;
-            Ok(Some(run))
+        let user: User = serde_json::from_value(data)?;
+        Ok(user.login)
+    }
+
+    pub(crate) async fn get_workflow_file(&self, path: &str, ref_name: &str) -> Result<String, GarError> {
+        let url = self.repo_api_url(&format!("/contents/{}?ref={}", path, encode_query_value(ref_name)));
+        let data = self.github_request_bytes(&url, "GET", None, Some("application/vnd.github.raw")).await?;
+        Ok(String::from_utf8_lossy(&data).into_owned())
+    }
+
+    pub(crate) async fn get_run_artifacts(&self, run_id: u64) -> Result<Vec<Artifact>, GarError> {
+        let url = self.repo_api_url(&format!("/actions/runs/{}/artifacts", run_id));
+        self.github_request_paginated(&url, "artifacts", None).await
+    }
+
+    // Artifacts of all runs of the repository
+    pub(crate) async fn get_artifacts(&self) -> Result<Vec<Artifact>, GarError> {
+        let url = self.repo_api_url("/actions/artifacts");
+        self.github_request_paginated(&url, "artifacts", None).await
+    }
+
+    pub(crate) async fn delete_artifact(&self, artifact_id: u64) -> Result<(), GarError> {
+        let url = self.repo_api_url(&format!("/actions/artifacts/{}", artifact_id));
+        self.github_request(&url, "DELETE", None, None).await?;
+        Ok(())
+    }
+
+    // `key` matches cache keys by prefix, `ref_name` is a full ref such as refs/heads/main
+    pub(crate) async fn get_caches(&self, key: Option<&str>, ref_name: Option<&str>) -> Result<Vec<Cache>, GarError> {
+        let query = [("key", key), ("ref", ref_name)].iter()
+            .filter_map(|(name, value)| value.map(|value| format!("{}={}", name, encode_query_value(value))))
+            .collect::<Vec<_>>()
+            .join("&");
+        let mut url = self.repo_api_url("/actions/caches");
+        if !query.is_empty() {
+            url = format!("{}?{}", url, query);
+        }
+        self.github_request_paginated(&url, "actions_caches", None).await
+    }
+
+    pub(crate) async fn delete_cache(&self, cache_id: u64) -> Result<(), GarError> {
+        let url = self.repo_api_url(&format!("/actions/caches/{}", cache_id));
+        self.github_request(&url, "DELETE", None, None).await?;
+        Ok(())
+    }
+
+    pub(crate) async fn get_environments(&self) -> Result<Vec<String>, GarError> {
+        let url = self.repo_api_url("/environments");
+        let environments: Vec<Environment> = self.github_request_paginated(&url, "environments", None).await?;
+        Ok(environments.into_iter().map(|environment| environment.name).collect())
+    }
+
+    pub(crate) async fn get_workflow_run(&self, run_id: u64) -> Result<WorkflowRun, GarError> {
+        let url = self.repo_api_url(&format!("/actions/runs/{}", run_id));
+        match self.github_request(&url, "GET", None, None).await {
+            Ok(data) => Ok(serde_json::from_value(data)?),
+            Err(GarError::Http { status: 404, .. }) => Err(GarError::Selection(format!("Run {} not found in {}/{}", run_id, self.owner, self.repo))),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GitHub, Workflow};
+    use crate::error::GarError;
+
+    fn workflows() -> Vec<Workflow> {
+        [
+            (1, "CI", "ci.yml"),
+            (2, "Deploy production", "deploy.yml"),
+            (3, "Deploy staging", "deploy-staging.yaml"),
+            (4, "Release", "release.yml"),
+            (5, "Nightly build", "nightly.yml"),
+            (6, "Build app", "build-app.yml"),
+            (7, "Build api", "build-api.yml"),
+        ].into_iter().map(|(id, name, file)| Workflow {
+            id,
+            name: name.to_string(),
+            path: format!(".github/workflows/{}", file),
+            html_url: String::new(),
+        }).collect()
+    }
+
+    #[test]
+    fn finds_workflows() {
+        // query, the file of the workflow found or part of the error
+        let cases = [
+            // Exact: id, path, file name, file stem and name
+            ("4", Ok("release.yml")),
+            (".github/workflows/nightly.yml", Ok("nightly.yml")),
+            ("deploy-staging.yaml", Ok("deploy-staging.yaml")),
+            ("ci", Ok("ci.yml")),
+            ("Release", Ok("release.yml")),
+            // An exact stem wins over the substring matches of both deploy workflows
+            ("deploy", Ok("deploy.yml")),
+            // Substrings
+            ("staging", Ok("deploy-staging.yaml")),
+            ("NIGHT", Ok("nightly.yml")),
+            ("depl", Err("matches several workflows")),
+            ("build-ap", Err("matches several workflows")),
+            // Typos: 0.967 beats the runner-up at 0.852
+            ("deplyo", Ok("deploy.yml")),
+            ("relaese", Ok("release.yml")),
+            // 0.974 against 0.919 just clears the 0.05 margin, a tie does not
+            ("buidl-app", Ok("build-app.yml")),
+            ("build-apx", Err("matches several workflows")),
+            // 0.745 is below the 0.85 threshold
+            ("tset", Err("No workflow matches \"tset\"")),
+            ("xyz", Err("No workflow matches \"xyz\"")),
+        ];
+
+        let workflows = workflows();
+        for (query, expected) in cases {
+            match (GitHub::find_workflow(&workflows, query), expected) {
+                (Ok(workflow), Ok(file)) => assert_eq!(workflow.path, format!(".github/workflows/{}", file), "{}", query),
+                (Err(GarError::Selection(message)), Err(fragment)) => assert!(message.contains(fragment), "{}: {}", query, message),
+                (Ok(workflow), _) => panic!("{} unexpectedly matched {}", query, workflow.path),
+                (Err(e), _) => panic!("{} failed with {}", query, e),
+            }
         }
     }
 }
@@ -1,53 +1,243 @@
-use std::io::{Cursor, Read};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Seek};
 #[cfg(feature = "rodio")]
-use std::io::BufReader;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
 use zip::ZipArchive;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use chrono::{DateTime, Utc};
 use indicatif::ProgressBar;
 #[cfg(feature = "rodio")]
 use rodio::{Decoder, OutputStream, Sink};
 use tokio::sync::Mutex;
+use crate::error::GarError;
 
-pub fn unzip_and_concatenate(data_bytes: Vec<u8>) -> Result<String, Box<dyn std::error::Error>> {
-    let cursor = Cursor::new(data_bytes);
-    let mut archive = ZipArchive::new(cursor)?;
+// What `unzip_and_concatenate` reports. File boundaries are not lines, so no log content can be mistaken for one
+pub(crate) enum LogEvent<'a> {
+    // A file in a subfolder starts, e.g. "build/3_Run tests.txt"
+    Entry(&'a str),
+    Line(&'a str),
+}
 
-    let mut result = String::new();
+// Calls `event` for every line of a log archive without holding more than one line in memory:
+// the files at the root first, then the files in subfolders, each introduced by an `Entry`
+pub fn unzip_and_concatenate<R: Read + Seek>(reader: R, mut event: impl FnMut(LogEvent) -> io::Result<()>) -> Result<(), GarError> {
+    let mut archive = ZipArchive::new(reader)?;
 
     for i in 0..archive.len() {
-        let mut file = archive.by_index(i)?;
+        let file = archive.by_index(i)?;
         let file_name = file.name().to_string();
 
         // Пропустить файлы в поддиректориях, пока не обработаем все файлы в корне
-        if file_name.contains("/") {
+        if file_name.contains("/") || file.is_dir() {
             continue;
         }
 
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-        result.push_str(&contents);
+        for_each_line_lossy(file, |_, text| event(LogEvent::Line(text))).map_err(|e| GarError::Archive(format!("{}: {}", file_name, e)))?;
     }
 
     for i in 0..archive.len() {
-        let mut file = archive.by_index(i)?;
+        let file = archive.by_index(i)?;
         let file_name = file.name().to_string();
 
         // Теперь обрабатываем только файлы в поддиректориях
-        if !file_name.contains("/") {
+        if !file_name.contains("/") || file.is_dir() {
+            continue;
+        }
+
+        event(LogEvent::Entry(&file_name))?;
+        for_each_line_lossy(file, |_, text| event(LogEvent::Line(text))).map_err(|e| GarError::Archive(format!("{}: {}", file_name, e)))?;
+    }
+
+    Ok(())
+}
+
+// Reads line by line, replacing invalid UTF-8 instead of failing on it. `line` gets 1-based line numbers
+pub(crate) fn for_each_line_lossy<R: Read>(reader: R, mut line: impl FnMut(usize, &str) -> io::Result<()>) -> io::Result<()> {
+    let mut reader = BufReader::new(reader);
+    let mut buffer = Vec::new();
+    let mut number = 0;
+    loop {
+        buffer.clear();
+        if reader.read_until(b'\n', &mut buffer)? == 0 {
+            return Ok(());
+        }
+        number += 1;
+        let text = String::from_utf8_lossy(&buffer);
+        line(number, text.trim_end_matches(['\n', '\r']))?;
+    }
+}
+
+// A file in a temporary location that is removed when dropped, unless it was renamed away
+pub(crate) struct TempFile {
+    pub(crate) path: PathBuf,
+}
+
+impl TempFile {
+    pub(crate) fn create_in(dir: &Path, name: &str) -> Result<(TempFile, fs::File), GarError> {
+        fs::create_dir_all(dir)?;
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+        let path = dir.join(format!(".gar-{}-{}-{}.part", name, std::process::id(), nanos));
+        let file = fs::OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
+        Ok((TempFile { path }, file))
+    }
+
+    pub(crate) fn create(name: &str) -> Result<(TempFile, fs::File), GarError> {
+        Self::create_in(&std::env::temp_dir(), name)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Extracts an archive below `dir` and returns the relative paths of the extracted files.
+// Entries that would escape `dir` (absolute paths, `..`) are refused
+pub(crate) fn extract_archive<R: Read + Seek>(reader: R, dir: &Path) -> Result<Vec<PathBuf>, GarError> {
+    let mut archive = ZipArchive::new(reader)?;
+    let mut extracted = Vec::new();
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let relative = file.enclosed_name()
+            .ok_or_else(|| GarError::Archive(format!("Refusing to extract \"{}\" outside of the target directory", file.name())))?;
+        let path = dir.join(&relative);
+
+        if file.is_dir() {
+            fs::create_dir_all(&path)?;
             continue;
         }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut output = fs::File::create(&path)?;
+        io::copy(&mut file, &mut output).map_err(|e| GarError::Archive(format!("{}: {}", relative.display(), e)))?;
+        extracted.push(relative);
+    }
+
+    Ok(extracted)
+}
+
+pub(crate) fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+// Accepts plain bytes or a unit suffix such as "500KB", "10MB" or "1.5GB" (powers of 1024)
+pub(crate) fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().map_err(|_| format!("Invalid size \"{}\", expected e.g. 500KB, 10MB or 1GB", value))?;
+    let multiplier: u64 = match unit.trim().to_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        "T" | "TB" => 1 << 40,
+        unit => return Err(format!("Unknown size unit \"{}\" in \"{}\"", unit, value)),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+// Seconds since an RFC 3339 timestamp
+pub(crate) fn age_seconds(timestamp: &Option<String>) -> Option<i64> {
+    elapsed_seconds(timestamp, &None)
+}
+
+pub(crate) fn format_duration(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m {:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+// Seconds between two RFC 3339 timestamps, or since the start while the second one is missing
+pub(crate) fn elapsed_seconds(started_at: &Option<String>, completed_at: &Option<String>) -> Option<i64> {
+    let parse = |value: &Option<String>| value.as_deref().and_then(|v| DateTime::parse_from_rfc3339(v).ok()).map(|d| d.with_timezone(&Utc));
+    match (parse(started_at), parse(completed_at)) {
+        (Some(started), Some(completed)) => Some((completed - started).num_seconds()),
+        (Some(started), None) => Some((Utc::now() - started).num_seconds()),
+        _ => None,
+    }
+}
+
+pub(crate) fn format_elapsed(started_at: &Option<String>, completed_at: &Option<String>) -> String {
+    elapsed_seconds(started_at, completed_at).map(format_duration).unwrap_or_default()
+}
 
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-        result.push_str("\n");
-        result.push_str("--------------\n");
-        result.push_str(&file_name);
-        result.push_str("\n--------------\n");
-        result.push_str(&contents);
+// Accepts plain seconds or unit-suffixed parts such as "90s", "15m", "1h30m" or "7d"
+// Durations are capped at i64::MAX seconds, so callers can compare them with signed timestamps
+pub(crate) fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let too_long = || format!("Duration \"{}\" is too long", value);
+    let total = match value.parse::<u64>() {
+        Ok(seconds) => seconds,
+        Err(_) => {
+            let mut total = 0u64;
+            let mut number = String::new();
+            for c in value.chars() {
+                if c.is_ascii_digit() {
+                    number.push(c);
+                    continue;
+                }
+                let unit = match c {
+                    's' => 1,
+                    'm' => 60,
+                    'h' => 3600,
+                    'd' => 86400,
+                    'w' => 604800,
+                    _ => return Err(format!("Unknown duration unit '{}' in \"{}\"", c, value)),
+                };
+                let amount: u64 = number.parse().map_err(|_| format!("Invalid duration \"{}\"", value))?;
+                total = amount.checked_mul(unit).and_then(|seconds| total.checked_add(seconds)).ok_or_else(too_long)?;
+                number.clear();
+            }
+            if !number.is_empty() {
+                return Err(format!("Invalid duration \"{}\", expected e.g. 90s, 15m or 1h30m", value));
+            }
+            total
+        }
+    };
+    if total == 0 {
+        return Err(format!("Invalid duration \"{}\", expected e.g. 90s, 15m or 1h30m", value));
     }
+    if total > i64::MAX as u64 {
+        return Err(too_long());
+    }
+    Ok(Duration::from_secs(total))
+}
+
+// Closest candidate by Jaro-Winkler similarity, used for "did you mean" hints
+fn suggest<'a>(value: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    candidates.into_iter()
+        .map(|candidate| (candidate, strsim::jaro_winkler(&value.to_lowercase(), &candidate.to_lowercase())))
+        .filter(|(_, score)| *score >= 0.8)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(candidate, _)| candidate)
+}
 
-    Ok(result)
+// Appends a "did you mean" hint to `message` when one of `candidates` is close to `value`
+pub(crate) fn did_you_mean<'a>(message: String, value: &str, candidates: impl IntoIterator<Item = &'a str>) -> String {
+    match suggest(value, candidates) {
+        Some(suggestion) => format!("{}. Did you mean \"{}\"?", message, suggestion),
+        None => message,
+    }
 }
 
 pub(crate) async fn update_progress_bar(pb: Arc<Mutex<ProgressBar>>) {
@@ -60,15 +250,46 @@ pub(crate) async fn update_progress_bar(pb: Arc<Mutex<ProgressBar>>) {
     }
 }
 
+// A Ctrl-C listener that stays installed between `recv` calls. `tokio::signal::ctrl_c()` only listens while
+// it is awaited, so a signal arriving during a request would be dropped
+pub(crate) struct Interrupts {
+    #[cfg(unix)]
+    signal: tokio::signal::unix::Signal,
+    #[cfg(windows)]
+    signal: tokio::signal::windows::CtrlC,
+}
+
+impl Interrupts {
+    pub(crate) fn new() -> io::Result<Self> {
+        #[cfg(unix)]
+        let signal = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt())?;
+        #[cfg(windows)]
+        let signal = tokio::signal::windows::ctrl_c()?;
+        Ok(Interrupts { signal })
+    }
+
+    pub(crate) async fn recv(&mut self) {
+        // The stream only ends when the runtime shuts down; never report that as an interrupt
+        if self.signal.recv().await.is_none() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
 #[cfg(feature = "rodio")]
 pub(crate) fn beep(count: u8) {
-    let (_stream, handle) = OutputStream::try_default().unwrap();
+    // A missing audio device must not abort the command
+    let Ok((_stream, handle)) = OutputStream::try_default() else {
+        return;
+    };
     let beep_mp3_data = include_bytes!("../beep.mp3").to_vec();
     for _ in 0..count {
         let cursor = Cursor::new(beep_mp3_data.clone());
         match Decoder::new(BufReader::new(cursor)) {
             Ok(source) => {
-                let sink = Sink::try_new(&handle).unwrap();
+                let Ok(sink) = Sink::try_new(&handle) else {
+                    return;
+                };
                 sink.append(source);
                 sink.sleep_until_end();
             }
@@ -82,3 +303,89 @@ pub(crate) fn beep(count: u8) {
 
 #[cfg(not(feature = "rodio"))]
 pub(crate) fn beep(_count: u8) {}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+    use super::*;
+
+    fn zip_with(entries: &[(&str, &str)]) -> Cursor<Vec<u8>> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in entries {
+            writer.start_file(*name, SimpleFileOptions::default()).unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        let mut archive = writer.finish().unwrap();
+        archive.set_position(0);
+        archive
+    }
+
+    // A fresh directory per test, below a parent that is checked for escaped files
+    fn target_dir(name: &str) -> PathBuf {
+        let parent = std::env::temp_dir().join(format!("gar-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&parent);
+        fs::create_dir_all(parent.join("target")).unwrap();
+        parent.join("target")
+    }
+
+    #[test]
+    fn extracts_archives() {
+        let dir = target_dir("extract");
+        let files = extract_archive(zip_with(&[("1_build.txt", "job log"), ("build/1_Checkout.txt", "step log")]), &dir).unwrap();
+        assert_eq!(files, [PathBuf::from("1_build.txt"), PathBuf::from("build/1_Checkout.txt")]);
+        assert_eq!(fs::read_to_string(dir.join("build/1_Checkout.txt")).unwrap(), "step log");
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn refuses_entries_outside_of_the_target() {
+        for (index, name) in ["../evil.txt", "build/../../evil.txt", "/evil.txt"].into_iter().enumerate() {
+            let dir = target_dir(&format!("zip-slip-{}", index));
+            let result = extract_archive(zip_with(&[("1_build.txt", "job log"), (name, "evil")]), &dir);
+            assert!(matches!(result, Err(GarError::Archive(_))), "{}", name);
+            assert!(!dir.parent().unwrap().join("evil.txt").exists(), "{}", name);
+            assert!(!Path::new("/evil.txt").exists(), "{}", name);
+            fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn parses_sizes() {
+        let cases = [("0", 0), ("512", 512), ("512B", 512), ("500KB", 500 << 10), ("10mb", 10 << 20), ("1.5GB", 3 << 29), ("2T", 2 << 40), (" 1 MB ", 1 << 20)];
+        for (value, bytes) in cases {
+            assert_eq!(parse_size(value), Ok(bytes), "{}", value);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_sizes() {
+        for value in ["", "MB", "10XB", "1.2.3MB", "-1MB"] {
+            assert!(parse_size(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn parses_durations() {
+        let cases = [("90", 90), ("90s", 90), ("15m", 900), ("1h30m", 5400), ("2d", 172800), ("1w", 604800), (" 45s ", 45)];
+        for (value, seconds) in cases {
+            assert_eq!(parse_duration(value), Ok(Duration::from_secs(seconds)), "{}", value);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        for value in ["", "0", "0s", "m", "10x", "1h30", "1.5h", "-5m"] {
+            assert!(parse_duration(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn rejects_durations_that_overflow() {
+        for value in ["300000000000000d", "18446744073709551615", "9223372036854775808", "18446744073709551615s1s"] {
+            assert_eq!(parse_duration(value), Err(format!("Duration \"{}\" is too long", value)), "{}", value);
+        }
+        assert_eq!(parse_duration("9223372036854775807"), Ok(Duration::from_secs(i64::MAX as u64)));
+    }
+}
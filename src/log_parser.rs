@@ -0,0 +1,328 @@
+use std::io::Write;
+use std::sync::LazyLock;
+use chrono::DateTime;
+use colored::Colorize;
+use regex::Regex;
+
+// Lines of job logs start with e.g. "2024-01-01T00:00:00.0000000Z "
+static TIMESTAMP: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(?:\.\d+)?Z) ?").unwrap());
+
+// Splits a log line into its timestamp, if it has one, and the text after it
+pub(crate) fn split_timestamp(line: &str) -> (Option<&str>, &str) {
+    let line = line.trim_start_matches('\u{feff}');
+    match TIMESTAMP.captures(line) {
+        Some(caps) => (caps.get(1).map(|timestamp| timestamp.as_str()), &line[caps[0].len()..]),
+        None => (None, line),
+    }
+}
+
+// Archive entries are named "<n>_<name>.txt": "3_Run tests.txt" -> "Run tests"
+pub(crate) fn strip_number(file_name: &str) -> &str {
+    let name = file_name.trim_end_matches(".txt");
+    match name.split_once('_') {
+        Some((number, rest)) if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) => rest,
+        _ => name,
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum TimestampMode {
+    // Drop the timestamps
+    #[default]
+    Strip,
+    // Show the time of day only
+    Short,
+    // Keep the original RFC 3339 timestamps
+    Full,
+}
+
+impl std::str::FromStr for TimestampMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "strip" => Ok(TimestampMode::Strip),
+            "short" => Ok(TimestampMode::Short),
+            "full" => Ok(TimestampMode::Full),
+            _ => Err(format!("Unknown timestamp mode \"{}\", expected strip, short or full", value)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct LogOptions {
+    pub(crate) expand_groups: bool,
+    pub(crate) timestamps: TimestampMode,
+}
+
+pub(crate) struct LogIssue {
+    pub(crate) step: String,
+    pub(crate) line: usize,
+    pub(crate) message: String,
+}
+
+enum Marker<'a> {
+    Group(&'a str),
+    EndGroup,
+    Error(&'a str),
+    Warning(&'a str),
+    Notice(&'a str),
+    Debug(&'a str),
+    Command(&'a str),
+    Text(&'a str),
+}
+
+impl<'a> Marker<'a> {
+    fn parse(text: &'a str) -> Marker<'a> {
+        let Some(rest) = text.strip_prefix("##[") else {
+            return Marker::Text(text);
+        };
+        let Some((name, message)) = rest.split_once(']') else {
+            return Marker::Text(text);
+        };
+        // Annotations may carry properties, e.g. `##[error file=a.rs,line=3]message`
+        match name.split_whitespace().next().unwrap_or("") {
+            "group" => Marker::Group(message),
+            "endgroup" => Marker::EndGroup,
+            "error" => Marker::Error(message),
+            "warning" => Marker::Warning(message),
+            "notice" => Marker::Notice(message),
+            "debug" => Marker::Debug(message),
+            "command" => Marker::Command(message),
+            _ => Marker::Text(text),
+        }
+    }
+}
+
+// Line-oriented parser: feed it lines in order, it writes the rendered output and collects errors
+pub(crate) struct LogParser {
+    options: LogOptions,
+    job: String,
+    step: String,
+    has_file_step: bool,
+    line: usize,
+    in_group: bool,
+    hidden: usize,
+    errors: Vec<LogIssue>,
+    warnings: usize,
+}
+
+impl LogParser {
+    pub(crate) fn new(options: LogOptions) -> Self {
+        LogParser {
+            options,
+            job: String::new(),
+            step: String::new(),
+            has_file_step: false,
+            line: 0,
+            in_group: false,
+            hidden: 0,
+            errors: Vec::new(),
+            warnings: 0,
+        }
+    }
+
+    // Names the step for lines that follow, e.g. the job whose log is fed next,
+    // and the number of its lines that were already fed. A group left open by the previous step is closed
+    pub(crate) fn set_step(&mut self, step: &str, line: usize, out: &mut impl Write) -> std::io::Result<()> {
+        self.close_group(out)?;
+        self.job = step.to_string();
+        self.step = step.to_string();
+        self.has_file_step = false;
+        self.line = line;
+        Ok(())
+    }
+
+    // Starts the next step file of an archive, e.g. "build/3_Run tests.txt"
+    pub(crate) fn start_file(&mut self, file_name: &str, out: &mut impl Write) -> std::io::Result<()> {
+        self.set_step(&Self::step_name(file_name), 0, out)?;
+        self.has_file_step = true;
+        writeln!(out)?;
+        writeln!(out, "{}", format!("═══ {} ═══", self.step).bold())
+    }
+
+    pub(crate) fn feed(&mut self, raw: &str, out: &mut impl Write) -> std::io::Result<()> {
+        self.line += 1;
+        let (timestamp, text) = match split_timestamp(raw.trim_end_matches('\r')) {
+            (Some(timestamp), text) => (self.format_timestamp(timestamp), text),
+            (None, text) => (String::new(), text),
+        };
+
+        let rendered = match Marker::parse(text) {
+            Marker::Group(title) => {
+                self.close_group(out)?;
+                self.in_group = true;
+                // Groups such as "Run npm test" name the step when the log has no file headers
+                if !self.has_file_step {
+                    self.step = if self.job.is_empty() { title.to_string() } else { format!("{} / {}", self.job, title) };
+                }
+                let arrow = if self.options.expand_groups { "▾" } else { "▸" };
+                return writeln!(out, "{}{} {}", timestamp, arrow.cyan(), title.bold());
+            }
+            Marker::EndGroup => return self.close_group(out),
+            Marker::Error(message) => {
+                self.errors.push(LogIssue { step: self.current_step(), line: self.line, message: message.to_string() });
+                return writeln!(out, "{}{}", timestamp, format!("Error: {}", message).red().bold());
+            }
+            Marker::Warning(message) => {
+                self.warnings += 1;
+                return writeln!(out, "{}{}", timestamp, format!("Warning: {}", message).yellow());
+            }
+            Marker::Notice(message) => format!("{}", format!("Notice: {}", message).cyan()),
+            Marker::Debug(message) => format!("{}", message.dimmed()),
+            Marker::Command(message) => format!("{}", message.blue()),
+            Marker::Text(message) => message.to_string(),
+        };
+
+        if self.in_group && !self.options.expand_groups {
+            self.hidden += 1;
+            return Ok(());
+        }
+        let indent = if self.in_group { "  " } else { "" };
+        writeln!(out, "{}{}{}", timestamp, indent, rendered)
+    }
+
+    fn close_group(&mut self, out: &mut impl Write) -> std::io::Result<()> {
+        if self.in_group && self.hidden > 0 {
+            writeln!(out, "{}", format!("  … {} lines collapsed, use --expand-groups to show them", self.hidden).dimmed())?;
+        }
+        self.in_group = false;
+        self.hidden = 0;
+        Ok(())
+    }
+
+    fn current_step(&self) -> String {
+        if self.step.is_empty() { "(unknown step)".to_string() } else { self.step.clone() }
+    }
+
+    // "build/3_Run tests.txt" -> "build / Run tests"
+    fn step_name(file_name: &str) -> String {
+        match file_name.rsplit_once('/') {
+            Some((job, step)) => format!("{} / {}", job, strip_number(step)),
+            None => strip_number(file_name).to_string(),
+        }
+    }
+
+    fn format_timestamp(&self, timestamp: &str) -> String {
+        match self.options.timestamps {
+            TimestampMode::Strip => String::new(),
+            TimestampMode::Full => format!("{} ", timestamp.dimmed()),
+            TimestampMode::Short => match DateTime::parse_from_rfc3339(timestamp) {
+                Ok(time) => format!("{} ", time.format("%H:%M:%S").to_string().dimmed()),
+                Err(_) => format!("{} ", timestamp.dimmed()),
+            },
+        }
+    }
+
+    // Writes the error summary and returns the number of errors found
+    pub(crate) fn finish(mut self, out: &mut impl Write) -> std::io::Result<usize> {
+        self.close_group(out)?;
+        if self.errors.is_empty() {
+            if self.warnings > 0 {
+                writeln!(out, "{}", format!("{} warning(s), no errors", self.warnings).yellow())?;
+            }
+            return Ok(0);
+        }
+
+        writeln!(out)?;
+        writeln!(out, "{}", format!("{} error(s), {} warning(s):", self.errors.len(), self.warnings).red().bold())?;
+        for issue in &self.errors {
+            writeln!(out, "  {} {} {}", format!("[{}]", issue.step).bold(), format!("line {}:", issue.line).dimmed(), issue.message.red())?;
+        }
+        Ok(self.errors.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Feeds `events`, where "file:" starts a step file and "job:" a job, and returns the output and error count
+    fn render(options: LogOptions, events: &[&str]) -> (String, usize) {
+        colored::control::set_override(false);
+        let mut parser = LogParser::new(options);
+        let mut out = Vec::new();
+        for event in events {
+            if let Some(file_name) = event.strip_prefix("file:") {
+                parser.start_file(file_name, &mut out).unwrap();
+            } else if let Some(job) = event.strip_prefix("job:") {
+                parser.set_step(job, 0, &mut out).unwrap();
+            } else {
+                parser.feed(event, &mut out).unwrap();
+            }
+        }
+        let errors = parser.finish(&mut out).unwrap();
+        (String::from_utf8(out).unwrap(), errors)
+    }
+
+    #[test]
+    fn parses_markers_with_properties() {
+        assert!(matches!(Marker::parse("##[error file=src/main.rs,line=3]boom"), Marker::Error("boom")));
+        assert!(matches!(Marker::parse("##[warning]careful"), Marker::Warning("careful")));
+        assert!(matches!(Marker::parse("##[group]Run tests"), Marker::Group("Run tests")));
+        assert!(matches!(Marker::parse("##[endgroup]"), Marker::EndGroup));
+        assert!(matches!(Marker::parse("##[unknown]text"), Marker::Text("##[unknown]text")));
+        assert!(matches!(Marker::parse("##[error no bracket"), Marker::Text(_)));
+        assert!(matches!(Marker::parse("plain"), Marker::Text("plain")));
+    }
+
+    #[test]
+    fn collapses_groups() {
+        let lines = ["##[group]Run tests", "one", "two", "##[endgroup]", "after"];
+        let (output, errors) = render(LogOptions::default(), &lines);
+        assert_eq!(output, "▸ Run tests\n  … 2 lines collapsed, use --expand-groups to show them\nafter\n");
+        assert_eq!(errors, 0);
+
+        let (output, _) = render(LogOptions { expand_groups: true, ..LogOptions::default() }, &lines);
+        assert_eq!(output, "▾ Run tests\n  one\n  two\nafter\n");
+    }
+
+    #[test]
+    fn closes_a_group_left_open_by_the_previous_job() {
+        let (output, _) = render(LogOptions::default(), &["job:build", "##[group]Compile", "one", "job:test", "two"]);
+        assert_eq!(output, "▸ Compile\n  … 1 lines collapsed, use --expand-groups to show them\ntwo\n");
+    }
+
+    #[test]
+    fn formats_timestamps() {
+        let line = "\u{feff}2024-05-13T09:41:07.1234567Z hello";
+        let render_with = |timestamps| render(LogOptions { timestamps, ..LogOptions::default() }, &[line]).0;
+        assert_eq!(render_with(TimestampMode::Strip), "hello\n");
+        assert_eq!(render_with(TimestampMode::Short), "09:41:07 hello\n");
+        assert_eq!(render_with(TimestampMode::Full), "2024-05-13T09:41:07.1234567Z hello\n");
+    }
+
+    #[test]
+    fn attributes_errors_to_steps() {
+        let (output, errors) = render(LogOptions::default(), &[
+            "file:build/1_Set up job.txt",
+            "ready",
+            "file:build/3_Run tests.txt",
+            "ok",
+            "##[error]test failed",
+            "##[warning]slow",
+            "job:deploy",
+            "##[group]Run deploy.sh",
+            "##[error file=deploy.sh,line=3]no credentials",
+        ]);
+        assert_eq!(errors, 2);
+        assert!(output.contains("═══ build / Run tests ═══"));
+        assert!(output.contains("2 error(s), 1 warning(s):"));
+        assert!(output.contains("[build / Run tests] line 2: test failed"));
+        assert!(output.contains("[deploy / Run deploy.sh] line 2: no credentials"));
+    }
+
+    #[test]
+    fn counts_warnings_without_errors() {
+        let (output, errors) = render(LogOptions::default(), &["##[warning]slow"]);
+        assert_eq!(errors, 0);
+        assert_eq!(output, "Warning: slow\n1 warning(s), no errors\n");
+    }
+
+    #[test]
+    fn names_steps_after_archive_entries() {
+        assert_eq!(LogParser::step_name("build/3_Run tests.txt"), "build / Run tests");
+        assert_eq!(LogParser::step_name("2_build.txt"), "build");
+        assert_eq!(strip_number("v1_notes.txt"), "v1_notes");
+    }
+}
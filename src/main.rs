@@ -1,36 +1,150 @@
 mod commands;
+mod error;
 mod github;
 mod git;
 mod helpers;
+mod log_parser;
+mod output;
+mod pager;
+mod rate_limit;
+mod watch;
+mod workflow;
 
-use crate::commands::{command::Command as BaseCommand, history::HistoryCommand, details::DetailsCommand, autocomplete::AutocompleteCommand, run_workflow::RunWorkflowCommand};
+use std::path::PathBuf;
+use std::time::Duration;
+use colored::Colorize;
+use crate::commands::{command::Command as BaseCommand, history::{HistoryCommand, RunQuery}, details::DetailsCommand, autocomplete::AutocompleteCommand, run_workflow::RunWorkflowCommand, cancel::CancelCommand, rerun::RerunCommand, logs_download::LogsDownloadCommand, logs_grep::{GrepOptions, LogsGrepCommand}, artifacts::{ArtifactsCommand, ArtifactsOptions}, cleanup::{CleanupArtifactsCommand, CleanupCachesCommand, CleanupOptions}, stats::{StatsCommand, StatsOptions}};
+use crate::error::GarError;
+use crate::log_parser::{LogOptions, TimestampMode};
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() {
+    if let Err(e) = run().await {
+        eprintln!("{} {}", "Error:".red().bold(), e);
+        std::process::exit(e.exit_code());
+    }
+}
+
+async fn run() -> Result<(), GarError> {
     let gar_command = BaseCommand::create_command();
     let matches = gar_command.get_matches();
 
-    let base_command = BaseCommand::new(&matches);
+    let base_command = BaseCommand::new(&matches)?;
 
     match matches.subcommand() {
-        Some(("history", _)) => {
-            let history_command = HistoryCommand::new(base_command);
+        Some(("history", sub_matches)) => {
+            let history_command = HistoryCommand::new(base_command, RunQuery::from_matches(sub_matches));
             history_command.run().await?;
             return Ok(());
         }
-        Some(("details", _)) => {
-            let details_command = DetailsCommand::new(base_command);
+        Some(("details", sub_matches)) => {
+            let run_id = sub_matches.get_one::<u64>("run").copied();
+            let job = sub_matches.get_one::<String>("job").cloned();
+            let failed = sub_matches.get_flag("failed");
+            let log_options = LogOptions {
+                expand_groups: sub_matches.get_flag("expand_groups"),
+                timestamps: sub_matches.get_one::<TimestampMode>("timestamps").copied().unwrap_or_default(),
+            };
+            let follow = sub_matches.get_flag("follow");
+            let pager = !sub_matches.get_flag("no_pager");
+            let details_command = DetailsCommand::new(base_command, run_id, job, failed, log_options, follow, pager);
             details_command.run().await?;
             return Ok(());
         }
+        Some(("cancel", sub_matches)) => {
+            let run_id = sub_matches.get_one::<u64>("run").copied();
+            let force = sub_matches.get_flag("force");
+            let cancel_command = CancelCommand::new(base_command, run_id, force);
+            cancel_command.run().await?;
+            return Ok(());
+        }
+        Some(("rerun", sub_matches)) => {
+            let run_id = sub_matches.get_one::<u64>("run").copied();
+            let failed = sub_matches.get_flag("failed");
+            let job = sub_matches.get_one::<String>("job").cloned();
+            let debug = sub_matches.get_flag("debug");
+            let rerun_command = RerunCommand::new(base_command, run_id, failed, job, debug);
+            rerun_command.run().await?;
+            return Ok(());
+        }
+        Some(("logs", sub_matches)) => {
+            match sub_matches.subcommand() {
+                Some(("download", download_matches)) => {
+                    let run_id = download_matches.get_one::<u64>("run").copied();
+                    let out = download_matches.get_one::<PathBuf>("out").cloned().unwrap_or_default();
+                    let force = download_matches.get_flag("force");
+                    let logs_download_command = LogsDownloadCommand::new(base_command, run_id, out, force);
+                    logs_download_command.run().await?;
+                }
+                Some(("grep", grep_matches)) => {
+                    let options = GrepOptions {
+                        pattern: grep_matches.get_one::<String>("pattern").cloned().unwrap_or_default(),
+                        ignore_case: grep_matches.get_flag("ignore_case"),
+                        branch: grep_matches.get_one::<String>("branch").cloned(),
+                        status: grep_matches.get_one::<String>("status").cloned(),
+                        limit: grep_matches.get_one::<usize>("limit").copied(),
+                        concurrency: grep_matches.get_one::<usize>("concurrency").copied(),
+                        no_cache: grep_matches.get_flag("no_cache"),
+                    };
+                    let logs_grep_command = LogsGrepCommand::new(base_command, options);
+                    logs_grep_command.run().await?;
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+        Some(("artifacts", sub_matches)) => {
+            let options = ArtifactsOptions {
+                run_id: sub_matches.get_one::<u64>("run").copied(),
+                latest: sub_matches.get_flag("latest"),
+                name: sub_matches.get_one::<String>("name").cloned(),
+                all: sub_matches.get_flag("all"),
+                list: sub_matches.get_flag("list"),
+                out: sub_matches.get_one::<PathBuf>("out").cloned().unwrap_or_default(),
+            };
+            let artifacts_command = ArtifactsCommand::new(base_command, options);
+            artifacts_command.run().await?;
+            return Ok(());
+        }
+        Some(("cleanup", sub_matches)) => {
+            if let Some((kind, cleanup_matches)) = sub_matches.subcommand() {
+                // `--ref` is only a filter here, so the raw value is used rather than the current branch default
+                let options = CleanupOptions {
+                    name: cleanup_matches.try_get_one::<String>("name").ok().flatten().cloned(),
+                    key: cleanup_matches.try_get_one::<String>("key").ok().flatten().cloned(),
+                    ref_name: cleanup_matches.get_one::<String>("ref").cloned(),
+                    older_than: cleanup_matches.get_one::<Duration>("older_than").copied(),
+                    larger_than: cleanup_matches.get_one::<u64>("larger_than").copied(),
+                    dry_run: cleanup_matches.get_flag("dry_run"),
+                };
+                match kind {
+                    "artifacts" => CleanupArtifactsCommand::new(base_command, options).run().await?,
+                    "caches" => CleanupCachesCommand::new(base_command, options).run().await?,
+                    _ => {}
+                }
+            }
+            return Ok(());
+        }
+        Some(("stats", sub_matches)) => {
+            let options = StatsOptions {
+                query: RunQuery::from_matches(sub_matches),
+                weekly: sub_matches.get_one::<String>("period").map(|period| period == "week").unwrap_or(true),
+                concurrency: sub_matches.get_one::<usize>("concurrency").copied(),
+            };
+            let stats_command = StatsCommand::new(base_command, options);
+            stats_command.run().await?;
+            return Ok(());
+        }
         Some(("autocomplete", _)) => {
             let autocomplete_command = AutocompleteCommand::new(base_command);
             autocomplete_command.run()?;
             return Ok(());
         }
         _ => {
-            let inputs = matches.get_one::<String>("inputs").map(|s| s.to_owned()).unwrap_or_else(String::new);
-            let run_workflow_command = RunWorkflowCommand::new(base_command, inputs);
+            let inputs = matches.get_one::<String>("inputs").map(|s| s.to_owned()).unwrap_or_default();
+            let correlation_input = matches.get_one::<String>("correlation_input").cloned();
+            let no_wait = matches.get_flag("no_wait");
+            let run_workflow_command = RunWorkflowCommand::new(base_command, inputs, correlation_input, no_wait);
             run_workflow_command.run().await?;
         },
     };
@@ -0,0 +1,88 @@
+use prettytable::{format, Cell, Row, Table};
+use serde::Serialize;
+use crate::error::GarError;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Csv,
+    Markdown,
+    Yaml,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "yaml" | "yml" => Ok(OutputFormat::Yaml),
+            _ => Err(format!("Unknown output format \"{}\", expected table, json, csv, markdown or yaml", value)),
+        }
+    }
+}
+
+impl OutputFormat {
+    pub(crate) fn is_table(&self) -> bool {
+        *self == OutputFormat::Table
+    }
+}
+
+// Prints a list of records: JSON and YAML serialize `items` as they are, the other formats show `headers` and `rows`
+pub(crate) fn print_records<T: Serialize>(output: OutputFormat, items: &T, headers: &[&str], rows: &[Vec<String>]) -> Result<(), GarError> {
+    match output {
+        OutputFormat::Table => {
+            let mut table = Table::new();
+            table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+            table.add_row(Row::new(headers.iter().map(|header| Cell::new(header)).collect()));
+            for row in rows {
+                table.add_row(Row::new(row.iter().map(|value| Cell::new(value)).collect()));
+            }
+            table.printstd();
+        }
+        OutputFormat::Csv => {
+            println!("{}", csv_line(headers.iter().copied()));
+            for row in rows {
+                println!("{}", csv_line(row.iter().map(|value| value.as_str())));
+            }
+        }
+        OutputFormat::Markdown => {
+            println!("| {} |", headers.join(" | "));
+            println!("|{}", "---|".repeat(headers.len()));
+            for row in rows {
+                let cells: Vec<String> = row.iter().map(|value| value.replace('|', "\\|").replace('\n', " ")).collect();
+                println!("| {} |", cells.join(" | "));
+            }
+        }
+        OutputFormat::Json | OutputFormat::Yaml => print_value(output, items)?,
+    }
+    Ok(())
+}
+
+// Prints a single structured value; only meaningful for JSON and YAML
+pub(crate) fn print_value<T: Serialize>(output: OutputFormat, value: &T) -> Result<(), GarError> {
+    match output {
+        OutputFormat::Yaml => {
+            let yaml = serde_yaml::to_string(value).map_err(|e| GarError::Decode(e.to_string()))?;
+            print!("{}", yaml);
+        }
+        _ => println!("{}", serde_json::to_string_pretty(value)?),
+    }
+    Ok(())
+}
+
+// RFC 4180: fields with separators, quotes or line breaks are quoted, quotes are doubled
+fn csv_line<'a>(values: impl Iterator<Item = &'a str>) -> String {
+    values.map(|value| {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }).collect::<Vec<_>>().join(",")
+}
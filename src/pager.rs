@@ -0,0 +1,78 @@
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Child, Command, Stdio};
+
+// -F quits right away when the output fits on one screen, -X keeps it on the screen afterwards
+const DEFAULT_PAGER: &str = "less -FRX";
+
+// Writes to `$PAGER` when stdout is a terminal, otherwise straight to stdout
+pub(crate) struct Pager {
+    child: Option<Child>,
+}
+
+impl Pager {
+    pub(crate) fn start(enabled: bool) -> Pager {
+        if !enabled || !io::stdout().is_terminal() {
+            return Pager { child: None };
+        }
+
+        let command = env::var("PAGER").ok().filter(|pager| !pager.trim().is_empty()).unwrap_or_else(|| DEFAULT_PAGER.to_string());
+        let mut parts = command.split_whitespace();
+        let Some(program) = parts.next() else {
+            return Pager { child: None };
+        };
+        // A missing pager is not worth failing the command for
+        let child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .spawn()
+            .ok();
+        Pager { child }
+    }
+
+    pub(crate) fn finish(mut self) -> io::Result<()> {
+        self.close()
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        if let Some(mut child) = self.child.take() {
+            // Closing stdin tells the pager the output is complete
+            drop(child.stdin.take());
+            child.wait()?;
+        } else {
+            io::stdout().flush()?;
+        }
+        Ok(())
+    }
+}
+
+// An error returned halfway through the output still waits for the pager, otherwise it would be left
+// running behind the shell prompt
+impl Drop for Pager {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+impl Write for Pager {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.child.as_mut().and_then(|child| child.stdin.as_mut()) {
+            // The pager was quit before reading everything, the rest is dropped
+            Some(stdin) => match stdin.write(buf) {
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(buf.len()),
+                result => result,
+            },
+            None => io::stdout().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.child.as_mut().and_then(|child| child.stdin.as_mut()) {
+            Some(stdin) => match stdin.flush() {
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                result => result,
+            },
+            None => io::stdout().flush(),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+
+pub(crate) const MAX_RETRIES: u32 = 5;
+// Longer waits, whether for the reset or a Retry-After, are reported instead of silently blocking the terminal
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+const SECONDARY_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+const BACKOFF_BASE: Duration = Duration::from_secs(1);
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+// Below this many remaining requests the polling loops start stretching their interval
+const LOW_BUDGET: u64 = 100;
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RateLimit {
+    pub(crate) limit: u64,
+    pub(crate) remaining: u64,
+    pub(crate) reset: u64,
+}
+
+pub(crate) enum Retry {
+    After(Duration, String),
+    Exhausted(String),
+    No,
+}
+
+impl RateLimit {
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Option<RateLimit> {
+        Some(RateLimit {
+            limit: header_u64(headers, "x-ratelimit-limit")?,
+            remaining: header_u64(headers, "x-ratelimit-remaining")?,
+            reset: header_u64(headers, "x-ratelimit-reset")?,
+        })
+    }
+
+    pub(crate) fn seconds_until_reset(&self) -> u64 {
+        self.reset.saturating_sub(now())
+    }
+
+    // Spreads the remaining budget over the time left until the reset once it runs low
+    pub(crate) fn poll_interval(&self, base: Duration) -> Duration {
+        if self.remaining >= LOW_BUDGET {
+            return base;
+        }
+        let spread = Duration::from_secs(self.seconds_until_reset() / self.remaining.max(1));
+        base.max(spread)
+    }
+
+    fn exhausted_message(&self) -> String {
+        let wait = self.seconds_until_reset();
+        format!(
+            "GitHub API rate limit exhausted ({} requests per hour). It resets in {}m {}s; try again later or use a token with a higher limit.",
+            self.limit, wait / 60, wait % 60
+        )
+    }
+}
+
+// Server errors are only retried for `idempotent` requests, rate limits mean the request was not processed at all.
+// `body` is only needed for a 403, where it tells a secondary rate limit apart from missing permissions
+pub(crate) fn retry_for(status: StatusCode, headers: &HeaderMap, body: Option<&str>, attempt: u32, idempotent: bool) -> Retry {
+    let rate_limit = RateLimit::from_headers(headers);
+    let retry_after = header_u64(headers, "retry-after").map(Duration::from_secs);
+
+    let limited = status == StatusCode::TOO_MANY_REQUESTS
+        || (status == StatusCode::FORBIDDEN && (retry_after.is_some()
+            || rate_limit.is_some_and(|rl| rl.remaining == 0)
+            || body.is_some_and(|body| body.to_lowercase().contains("rate limit"))));
+
+    if limited {
+        if let Some(wait) = retry_after {
+            if wait > MAX_RATE_LIMIT_WAIT {
+                return Retry::Exhausted(format!(
+                    "GitHub secondary rate limit hit and retrying is only allowed in {}m {}s; try again later.",
+                    wait.as_secs() / 60, wait.as_secs() % 60
+                ));
+            }
+            return Retry::After(wait, "GitHub secondary rate limit hit".to_string());
+        }
+        if let Some(rate_limit) = rate_limit.filter(|rl| rl.remaining == 0) {
+            let wait = Duration::from_secs(rate_limit.seconds_until_reset() + 1);
+            if wait > MAX_RATE_LIMIT_WAIT {
+                return Retry::Exhausted(rate_limit.exhausted_message());
+            }
+            return Retry::After(wait, "GitHub API rate limit hit".to_string());
+        }
+        return Retry::After(SECONDARY_RATE_LIMIT_WAIT.max(backoff(attempt)), "GitHub secondary rate limit hit".to_string());
+    }
+
+    if status.is_server_error() && idempotent {
+        return Retry::After(backoff(attempt), format!("GitHub responded with {}", status));
+    }
+
+    Retry::No
+}
+
+// Exponential backoff with up to a second of jitter so parallel clients do not retry in lockstep
+pub(crate) fn backoff(attempt: u32) -> Duration {
+    let exponential = BACKOFF_BASE.saturating_mul(2u32.saturating_pow(attempt)).min(BACKOFF_MAX);
+    let jitter = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_millis()).unwrap_or(0);
+    exponential + Duration::from_millis(jitter as u64)
+}
+
+fn header_u64(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::{HeaderMap, HeaderValue};
+    use reqwest::StatusCode;
+    use super::{now, retry_for, Retry};
+
+    fn headers(pairs: &[(&'static str, String)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn rate_limit_headers(remaining: u64, reset_in: u64) -> HeaderMap {
+        headers(&[
+            ("x-ratelimit-limit", "5000".to_string()),
+            ("x-ratelimit-remaining", remaining.to_string()),
+            ("x-ratelimit-reset", (now() + reset_in).to_string()),
+        ])
+    }
+
+    #[test]
+    fn retries_secondary_rate_limits_told_by_the_body() {
+        let body = r#"{"message":"You have exceeded a secondary rate limit. Please wait a few minutes before you try again."}"#;
+        let retry = retry_for(StatusCode::FORBIDDEN, &rate_limit_headers(4000, 3000), Some(body), 0, false);
+        assert!(matches!(retry, Retry::After(wait, _) if wait.as_secs() == 60));
+    }
+
+    #[test]
+    fn does_not_retry_permission_errors() {
+        let body = r#"{"message":"Resource not accessible by integration"}"#;
+        let retry = retry_for(StatusCode::FORBIDDEN, &rate_limit_headers(4000, 3000), Some(body), 0, true);
+        assert!(matches!(retry, Retry::No));
+    }
+
+    #[test]
+    fn caps_retry_after() {
+        let short = headers(&[("retry-after", "5".to_string())]);
+        assert!(matches!(retry_for(StatusCode::TOO_MANY_REQUESTS, &short, None, 0, false), Retry::After(wait, _) if wait.as_secs() == 5));
+        let long = headers(&[("retry-after", "3600".to_string())]);
+        assert!(matches!(retry_for(StatusCode::FORBIDDEN, &long, Some(""), 0, false), Retry::Exhausted(_)));
+    }
+
+    #[test]
+    fn reports_an_exhausted_primary_limit() {
+        assert!(matches!(retry_for(StatusCode::FORBIDDEN, &rate_limit_headers(0, 3000), Some(""), 0, false), Retry::Exhausted(_)));
+        assert!(matches!(retry_for(StatusCode::FORBIDDEN, &rate_limit_headers(0, 10), Some(""), 0, false), Retry::After(_, _)));
+    }
+
+    #[test]
+    fn retries_server_errors_of_idempotent_requests_only() {
+        assert!(matches!(retry_for(StatusCode::BAD_GATEWAY, &HeaderMap::new(), None, 0, true), Retry::After(_, _)));
+        assert!(matches!(retry_for(StatusCode::BAD_GATEWAY, &HeaderMap::new(), None, 0, false), Retry::No));
+    }
+}
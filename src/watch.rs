@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use colored::Colorize;
+use dialoguer::Select;
+use dialoguer::theme::ColorfulTheme;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use tokio::sync::Mutex;
+use crate::error::GarError;
+use crate::github::{GitHub, Job, Step, WorkflowRun};
+use crate::helpers::{beep, format_duration, format_elapsed, update_progress_bar, Interrupts};
+
+// Jobs and their steps rendered as lines under the spinner, updated in place
+struct JobTree {
+    multi: MultiProgress,
+    jobs: HashMap<u64, (ProgressBar, Vec<ProgressBar>)>,
+}
+
+impl JobTree {
+    fn new(multi: MultiProgress) -> Self {
+        JobTree { multi, jobs: HashMap::new() }
+    }
+
+    fn line() -> ProgressBar {
+        let bar = ProgressBar::new_spinner();
+        bar.set_style(ProgressStyle::with_template("{msg}").unwrap());
+        bar
+    }
+
+    fn update(&mut self, jobs: &[Job]) {
+        let JobTree { multi, jobs: bars } = self;
+        for job in jobs {
+            let (job_bar, step_bars) = bars.entry(job.id).or_insert_with(|| (multi.add(Self::line()), Vec::new()));
+            job_bar.set_message(format!("{} {} {}", icon(&job.status, job.conclusion.as_deref()), job.name.bold(), format_elapsed(&job.started_at, &job.completed_at).dimmed()));
+
+            for (index, step) in job.steps.iter().enumerate() {
+                if index >= step_bars.len() {
+                    let after = step_bars.last().unwrap_or(job_bar);
+                    let bar = multi.insert_after(after, Self::line());
+                    step_bars.push(bar);
+                }
+                step_bars[index].set_message(step_line(step));
+            }
+        }
+    }
+
+    fn finish(&self) {
+        for (job_bar, step_bars) in self.jobs.values() {
+            job_bar.finish();
+            for bar in step_bars {
+                bar.finish();
+            }
+        }
+    }
+}
+
+fn icon(status: &str, conclusion: Option<&str>) -> String {
+    match (status, conclusion) {
+        ("completed", Some("success")) => "✓".green().to_string(),
+        ("completed", Some("failure")) | ("completed", Some("timed_out")) | ("completed", Some("startup_failure")) => "✗".red().to_string(),
+        ("completed", Some("cancelled")) => "⊘".yellow().to_string(),
+        ("completed", Some("skipped")) | ("completed", Some("neutral")) => "↷".dimmed().to_string(),
+        ("completed", _) => "?".dimmed().to_string(),
+        ("in_progress", _) => "●".yellow().to_string(),
+        _ => "○".dimmed().to_string(),
+    }
+}
+
+fn step_line(step: &Step) -> String {
+    format!("    {} {} {}", icon(&step.status, step.conclusion.as_deref()), step.name, format_elapsed(&step.started_at, &step.completed_at).dimmed())
+}
+
+enum Interrupt {
+    Cancel,
+    Detach,
+    Wait,
+}
+
+fn ask_on_interrupt(interactive: bool) -> Interrupt {
+    if !interactive {
+        return Interrupt::Detach;
+    }
+    let choices = ["Cancel the remote run", "Detach and leave the run going", "Keep waiting"];
+    let selected = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Interrupted. What should happen to the workflow run?")
+        .items(&choices)
+        .default(2)
+        .interact();
+    match selected {
+        Ok(0) => Interrupt::Cancel,
+        Ok(2) => Interrupt::Wait,
+        // A second Ctrl-C while prompting means "get me out of here"
+        _ => Interrupt::Detach,
+    }
+}
+
+// Polls the run until it completes and returns its final state
+pub(crate) async fn wait_for_run(github: &GitHub, run_id: u64, timeout: Option<Duration>) -> Result<WorkflowRun, GarError> {
+    let multi = MultiProgress::new();
+    let pb = Arc::new(Mutex::new(multi.add(ProgressBar::new_spinner())));
+    pb.lock().await.set_style(ProgressStyle::default_spinner()
+        .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏")
+        .template("{spinner} {msg}").unwrap());
+    pb.lock().await.set_message("Waiting for the workflow run to complete...");
+    let mut tree = JobTree::new(multi.clone());
+
+    let pb_clone = Arc::clone(&pb);
+    let ticker = tokio::spawn(async move {
+        update_progress_bar(pb_clone).await;
+    });
+
+    let result = poll_run(github, run_id, timeout, &multi, &pb, &mut tree).await;
+
+    ticker.abort();
+    tree.finish();
+    let pb = pb.lock().await;
+    match &result {
+        Ok(run) => {
+            pb.finish_with_message("GitHub action completed");
+            println!();
+            println!("GitHub action completed with conclusion: {}", run.conclusion.clone().unwrap_or_else(|| "unknown".to_string()));
+            beep(3);
+        }
+        Err(_) => pb.abandon_with_message("Stopped waiting for the workflow run"),
+    }
+    result
+}
+
+async fn poll_run(
+    github: &GitHub,
+    run_id: u64,
+    timeout: Option<Duration>,
+    multi: &MultiProgress,
+    pb: &Mutex<ProgressBar>,
+    tree: &mut JobTree,
+) -> Result<WorkflowRun, GarError> {
+    // A timeout beyond what Instant can represent is as good as none
+    let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+    let run_url = github.repo_web_url(&format!("/actions/runs/{}", run_id));
+
+    // One listener for the whole wait, so a Ctrl-C during a request or a retry backoff is not lost
+    let mut interrupts = Interrupts::new()?;
+
+    // Wait for the workflow run to complete.
+    loop {
+        tokio::select! {
+            run = poll_once(github, run_id, deadline, timeout, &run_url, tree) => {
+                if let Some(run) = run? {
+                    return Ok(run);
+                }
+            }
+            _ = interrupts.recv() => {
+                match multi.suspend(|| ask_on_interrupt(github.is_interactive())) {
+                    Interrupt::Cancel => {
+                        github.cancel_run(run_id, false).await?;
+                        pb.lock().await.set_message("Cancellation requested, waiting for the run to stop...");
+                    }
+                    Interrupt::Detach => {
+                        return Err(GarError::Interrupted(format!("Detached; the workflow run keeps going: {}", run_url)));
+                    }
+                    Interrupt::Wait => {}
+                }
+            }
+        }
+    }
+}
+
+// One round of the wait: refreshes the run and the job tree, then sleeps until the next round
+async fn poll_once(
+    github: &GitHub,
+    run_id: u64,
+    deadline: Option<Instant>,
+    timeout: Option<Duration>,
+    run_url: &str,
+    tree: &mut JobTree,
+) -> Result<Option<WorkflowRun>, GarError> {
+    let run = github.get_workflow_run(run_id).await?;
+    // A failing jobs request should not interrupt the wait, the tree just stays stale
+    if let Ok(jobs) = github.get_run_jobs(run_id).await {
+        tree.update(&jobs);
+    }
+
+    if matches!(run.status.as_str(), "completed" | "failure") {
+        return Ok(Some(run));
+    }
+
+    let mut interval = github.poll_interval(Duration::from_secs(5));
+    if let Some(deadline) = deadline {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(GarError::Timeout(format!(
+                "The workflow run did not complete within {}; it is still running: {}",
+                format_duration(timeout.unwrap_or_default().as_secs() as i64), run_url
+            )));
+        }
+        interval = interval.min(remaining);
+    }
+    tokio::time::sleep(interval).await;
+    Ok(None)
+}
+
+// Successful conclusions map to Ok, everything else to an error carrying its own exit code
+pub(crate) fn conclusion_result(run: &WorkflowRun) -> Result<(), GarError> {
+    match run.conclusion.as_deref() {
+        Some("success") | Some("neutral") | Some("skipped") => Ok(()),
+        conclusion => Err(GarError::RunConclusion {
+            conclusion: conclusion.unwrap_or("unknown").to_string(),
+            url: run.html_url.clone(),
+        }),
+    }
+}
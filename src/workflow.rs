@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+use std::fs;
+use colored::Colorize;
+use dialoguer::{Confirm, Input, Select};
+use dialoguer::theme::ColorfulTheme;
+use serde::Deserialize;
+use serde_yaml::Value;
+use crate::error::GarError;
+use crate::github::{GitHub, Workflow};
+use crate::helpers::did_you_mean;
+
+// GitHub rejects dispatches with more inputs than this
+const MAX_DISPATCH_INPUTS: usize = 10;
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum InputType {
+    #[default]
+    String,
+    Choice,
+    Boolean,
+    Number,
+    Environment,
+}
+
+// One entry of `on.workflow_dispatch.inputs`
+#[derive(Deserialize, Clone, Debug)]
+pub(crate) struct WorkflowInput {
+    #[serde(skip)]
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) description: Option<String>,
+    #[serde(default)]
+    pub(crate) required: bool,
+    #[serde(default)]
+    pub(crate) default: Option<Value>,
+    #[serde(default, rename = "type")]
+    pub(crate) input_type: InputType,
+    #[serde(default)]
+    pub(crate) options: Vec<String>,
+}
+
+impl WorkflowInput {
+    pub(crate) fn default_value(&self) -> Option<String> {
+        match self.default.as_ref()? {
+            Value::String(value) => Some(value.clone()),
+            Value::Bool(value) => Some(value.to_string()),
+            Value::Number(value) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+
+    fn validate(&self, value: &str) -> Result<(), String> {
+        match self.input_type {
+            InputType::Choice if !self.options.is_empty() && !self.options.iter().any(|o| o == value) => {
+                let message = did_you_mean(format!("Input \"{}\" must be one of [{}], got \"{}\"", self.name, self.options.join(", "), value), value, self.options.iter().map(|o| o.as_str()));
+                Err(message)
+            }
+            InputType::Boolean if value != "true" && value != "false" => {
+                Err(format!("Input \"{}\" must be true or false, got \"{}\"", self.name, value))
+            }
+            InputType::Number if value.parse::<f64>().is_err() => {
+                Err(format!("Input \"{}\" must be a number, got \"{}\"", self.name, value))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn prompt_label(&self) -> String {
+        let mut label = self.name.clone();
+        if let Some(description) = self.description.as_ref().filter(|d| !d.trim().is_empty()) {
+            label = format!("{} ({})", label, description.trim());
+        }
+        if self.required {
+            label = format!("{} {}", label, "*".red());
+        }
+        label
+    }
+
+    // Returns None when an optional input is left empty so the workflow's own default applies
+    pub(crate) async fn prompt(&self, github: &GitHub) -> Result<Option<String>, GarError> {
+        let theme = ColorfulTheme::default();
+        let default = self.default_value();
+
+        let value = match self.input_type {
+            InputType::Choice if !self.options.is_empty() => {
+                let selected = Select::with_theme(&theme)
+                    .with_prompt(self.prompt_label())
+                    .items(&self.options)
+                    .default(default.as_ref().and_then(|d| self.options.iter().position(|o| o == d)).unwrap_or(0))
+                    .interact()?;
+                self.options[selected].clone()
+            }
+            InputType::Boolean => {
+                let confirmed = Confirm::with_theme(&theme)
+                    .with_prompt(self.prompt_label())
+                    .default(default.as_deref() == Some("true"))
+                    .interact()?;
+                confirmed.to_string()
+            }
+            InputType::Environment => {
+                let environments = github.get_environments().await.unwrap_or_default();
+                if environments.is_empty() {
+                    self.prompt_text(&theme, default)?
+                } else {
+                    let selected = Select::with_theme(&theme)
+                        .with_prompt(self.prompt_label())
+                        .items(&environments)
+                        .default(default.as_ref().and_then(|d| environments.iter().position(|e| e == d)).unwrap_or(0))
+                        .interact()?;
+                    environments[selected].clone()
+                }
+            }
+            _ => self.prompt_text(&theme, default)?,
+        };
+
+        if value.is_empty() && !self.required {
+            return Ok(None);
+        }
+        Ok(Some(value))
+    }
+
+    fn prompt_text(&self, theme: &ColorfulTheme, default: Option<String>) -> Result<String, GarError> {
+        let required = self.required;
+        let is_number = self.input_type == InputType::Number;
+
+        let mut input = Input::<String>::with_theme(theme)
+            .with_prompt(self.prompt_label())
+            .allow_empty(!required)
+            .validate_with(move |value: &String| -> Result<(), String> {
+                if value.is_empty() {
+                    return if required { Err("This input is required".to_string()) } else { Ok(()) };
+                }
+                if is_number && value.parse::<f64>().is_err() {
+                    return Err("Enter a number".to_string());
+                }
+                Ok(())
+            });
+        if let Some(default) = default {
+            input = input.default(default);
+        }
+
+        Ok(input.interact_text()?)
+    }
+}
+
+// Returns None when the workflow has no workflow_dispatch trigger
+pub(crate) fn parse_dispatch_inputs(yaml: &str) -> Result<Option<Vec<WorkflowInput>>, GarError> {
+    let document: Value = serde_yaml::from_str(yaml).map_err(|e| GarError::Decode(format!("Invalid workflow YAML: {}", e)))?;
+
+    // `on` may be a single event, a list of events or a map of event configurations
+    let dispatch = match &document["on"] {
+        Value::String(event) if event == "workflow_dispatch" => Value::Null,
+        Value::Sequence(events) if events.iter().any(|e| e.as_str() == Some("workflow_dispatch")) => Value::Null,
+        Value::Mapping(events) => match events.get("workflow_dispatch") {
+            Some(dispatch) => dispatch.clone(),
+            None => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+
+    let mut inputs = Vec::new();
+    if let Some(declared) = dispatch.get("inputs").and_then(|i| i.as_mapping()) {
+        for (name, definition) in declared {
+            let Some(name) = name.as_str() else { continue };
+            let mut input: WorkflowInput = match definition {
+                Value::Null => serde_yaml::from_str("{}"),
+                definition => serde_yaml::from_value(definition.clone()),
+            }.map_err(|e| GarError::Decode(format!("Invalid definition of input \"{}\": {}", name, e)))?;
+            input.name = name.to_string();
+            inputs.push(input);
+        }
+    }
+
+    Ok(Some(inputs))
+}
+
+// Reads the workflow file at the dispatched ref, falling back to the local checkout
+pub(crate) async fn load_dispatch_inputs(github: &GitHub, workflow: &Workflow, ref_name: &str) -> Result<Option<Vec<WorkflowInput>>, GarError> {
+    let yaml = match github.get_workflow_file(&workflow.path, ref_name).await {
+        Ok(yaml) => yaml,
+        Err(remote_error) => fs::read_to_string(&workflow.path)
+            .map_err(|_| remote_error)?,
+    };
+    parse_dispatch_inputs(&yaml)
+}
+
+pub(crate) async fn prompt_inputs(
+    github: &GitHub,
+    declared: &[WorkflowInput],
+    provided: &HashMap<String, String>,
+) -> Result<HashMap<String, String>, GarError> {
+    let mut inputs = provided.clone();
+    for input in declared.iter().filter(|input| !provided.contains_key(&input.name)) {
+        // Without a terminal the declared defaults are used; missing required inputs fail validation
+        let value = if github.is_interactive() {
+            input.prompt(github).await?
+        } else {
+            input.default_value()
+        };
+        if let Some(value) = value {
+            inputs.insert(input.name.clone(), value);
+        }
+    }
+    Ok(inputs)
+}
+
+// With `check_required` unset only the provided values are checked, so typos surface before any prompt
+pub(crate) fn validate_inputs(
+    declared: &[WorkflowInput],
+    provided: &HashMap<String, String>,
+    check_required: bool,
+) -> Result<(), GarError> {
+    let mut problems = Vec::new();
+
+    let mut names: Vec<&String> = provided.keys().collect();
+    names.sort();
+    for name in names {
+        match declared.iter().find(|input| &input.name == name) {
+            // An empty value is a missing one, reported below when the input is required
+            Some(_) if provided[name].is_empty() => {}
+            Some(input) => {
+                if let Err(problem) = input.validate(&provided[name]) {
+                    problems.push(problem);
+                }
+            }
+            None => {
+                let problem = did_you_mean(format!("Input \"{}\" is not declared by the workflow", name), name, declared.iter().map(|input| input.name.as_str()));
+                problems.push(problem);
+            }
+        }
+    }
+
+    if check_required {
+        for input in declared.iter().filter(|input| input.required) {
+            if provided.get(&input.name).is_none_or(|value| value.is_empty()) {
+                problems.push(format!("Required input \"{}\" is missing", input.name));
+            }
+        }
+    }
+
+    if provided.len() > MAX_DISPATCH_INPUTS {
+        problems.push(format!("{} inputs provided, GitHub accepts at most {}", provided.len(), MAX_DISPATCH_INPUTS));
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(GarError::InvalidInputs(problems))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORKFLOW: &str = r#"
+name: Deploy
+on:
+  push:
+  workflow_dispatch:
+    inputs:
+      environment:
+        description: Where to deploy
+        required: true
+        type: choice
+        options: [staging, production]
+      dry_run:
+        type: boolean
+        default: true
+      replicas:
+        type: number
+        default: 2
+      note:
+"#;
+
+    #[test]
+    fn parses_declared_inputs() {
+        let inputs = parse_dispatch_inputs(WORKFLOW).unwrap().unwrap();
+        let names: Vec<&str> = inputs.iter().map(|input| input.name.as_str()).collect();
+        assert_eq!(names, ["environment", "dry_run", "replicas", "note"]);
+
+        assert_eq!(inputs[0].input_type, InputType::Choice);
+        assert!(inputs[0].required);
+        assert_eq!(inputs[0].options, ["staging", "production"]);
+        assert_eq!(inputs[1].input_type, InputType::Boolean);
+        assert_eq!(inputs[1].default_value().as_deref(), Some("true"));
+        assert_eq!(inputs[2].default_value().as_deref(), Some("2"));
+        assert_eq!(inputs[3].input_type, InputType::String);
+        assert!(!inputs[3].required);
+    }
+
+    #[test]
+    fn parses_dispatch_without_inputs() {
+        for yaml in ["on: workflow_dispatch", "on: [push, workflow_dispatch]", "on:\n  workflow_dispatch:\n"] {
+            assert_eq!(parse_dispatch_inputs(yaml).unwrap().map(|inputs| inputs.len()), Some(0), "{}", yaml);
+        }
+    }
+
+    #[test]
+    fn reports_missing_dispatch_trigger() {
+        for yaml in ["on: push", "on: [push, pull_request]", "on:\n  push:\n    branches: [main]\n", "name: no trigger"] {
+            assert!(parse_dispatch_inputs(yaml).unwrap().is_none(), "{}", yaml);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_yaml() {
+        assert!(matches!(parse_dispatch_inputs("on: [workflow_dispatch"), Err(GarError::Decode(_))));
+    }
+
+    fn problems(provided: &[(&str, &str)], check_required: bool) -> Vec<String> {
+        let declared = parse_dispatch_inputs(WORKFLOW).unwrap().unwrap();
+        let provided: HashMap<String, String> = provided.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        match validate_inputs(&declared, &provided, check_required) {
+            Ok(()) => Vec::new(),
+            Err(GarError::InvalidInputs(problems)) => problems,
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn accepts_valid_inputs() {
+        assert!(problems(&[("environment", "staging"), ("dry_run", "false"), ("replicas", "3")], true).is_empty());
+        assert!(problems(&[("replicas", "1.5")], false).is_empty());
+    }
+
+    #[test]
+    fn reports_unknown_inputs_with_a_suggestion() {
+        assert_eq!(problems(&[("enviroment", "staging")], false), ["Input \"enviroment\" is not declared by the workflow. Did you mean \"environment\"?"]);
+        assert_eq!(problems(&[("xyz", "1")], false), ["Input \"xyz\" is not declared by the workflow"]);
+    }
+
+    #[test]
+    fn reports_missing_required_inputs() {
+        assert_eq!(problems(&[], true), ["Required input \"environment\" is missing"]);
+        assert_eq!(problems(&[("environment", "")], true), ["Required input \"environment\" is missing"]);
+        // Before the prompts only the provided values are checked
+        assert!(problems(&[], false).is_empty());
+    }
+
+    #[test]
+    fn reports_values_of_the_wrong_kind() {
+        assert_eq!(problems(&[("environment", "qa")], false), ["Input \"environment\" must be one of [staging, production], got \"qa\""]);
+        assert_eq!(problems(&[("environment", "productio")], false), ["Input \"environment\" must be one of [staging, production], got \"productio\". Did you mean \"production\"?"]);
+        assert_eq!(problems(&[("dry_run", "yes")], false), ["Input \"dry_run\" must be true or false, got \"yes\""]);
+        assert_eq!(problems(&[("replicas", "two")], false), ["Input \"replicas\" must be a number, got \"two\""]);
+    }
+
+    #[test]
+    fn reports_more_than_ten_inputs_and_every_problem_at_once() {
+        let names: Vec<String> = (0..11).map(|i| format!("extra_{}", i)).collect();
+        let provided: Vec<(&str, &str)> = names.iter().map(|name| (name.as_str(), "1")).collect();
+        let problems = problems(&provided, true);
+        assert_eq!(problems.len(), 13);
+        assert!(problems.contains(&"Required input \"environment\" is missing".to_string()));
+        assert_eq!(problems.last().unwrap(), "11 inputs provided, GitHub accepts at most 10");
+    }
+}